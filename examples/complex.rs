@@ -13,6 +13,7 @@ fn main() {
         
     let mut gen = Generator::new();
     gen.add_json(json).unwrap();
+    gen.set_typography(true);
     println!("{}", gen.instantiate("main").unwrap());
     println!("{}", gen.instantiate("main").unwrap());
 }
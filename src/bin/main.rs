@@ -1,6 +1,8 @@
-use genere::{Generator, Result, Gender};
+use genere::{Generator, Result, Gender, tracery_to_json};
 
 use std::env;
+use std::fs;
+use std::path::Path;
 use std::process::exit;
 use std::io::{self, Read};
 
@@ -10,15 +12,28 @@ fn help() -> ! {
 Genere, version {version}
 
 USAGE:
-genere <symbol>
-    instantiate the `symbol` in your JSON content.
-    Content is read from standard input. If you want to read from a file:
+genere [symbol] [file]
+    instantiate the `symbol` in your grammar file.
+    If no file is given, content is read from standard input, e.g.:
     genere some_symbol < file.json
+    If no symbol is given, the entry symbol set in the "$meta" section of the grammar
+    is used.
+
+OPTIONS:
+--format <format>
+    read the grammar in the given format: 'json' (default), 'yaml', 'toml' or 'ron'.
+    If it isn't specified, the format is guessed from the file extension.
+    YAML, TOML and RON support must be enabled at compile time with the
+    corresponding cargo features.
 
 OTHER USAGES:
 genere --help
     will display this help message instead of parsing content.
 
+genere --from-tracery [file]
+    will translate a Tracery grammar (read from the file, or from standard input)
+    into a genere grammar, in JSON format, and display it.
+
 genere --regender m
     will parse the content as a string instead of a JSON structure and will gender it
     according to the specified gender (can be 'm', 'f', or 'n').
@@ -28,6 +43,32 @@ genere --regender m
     exit(0);
 }
 
+// Display an error message and exit
+fn fail(msg: &str) -> ! {
+    println!("Error: {}", msg);
+    exit(1);
+}
+
+// Add the content to the generator, according to its format
+fn add_grammar(generator: &mut Generator, content: &str, format: &str) -> Result<()> {
+    match format {
+        "json" => generator.add_json(content),
+        #[cfg(feature = "yaml")]
+        "yaml" | "yml" => generator.add_yaml(content),
+        #[cfg(feature = "toml")]
+        "toml" => generator.add_toml(content),
+        #[cfg(feature = "ron")]
+        "ron" => generator.add_ron(content),
+        // Only reachable if some features are disabled
+        #[allow(unreachable_patterns)]
+        "yaml" | "yml" | "toml" | "ron" => fail(&format!(
+            "genere was compiled without support for the '{}' format",
+            format
+        )),
+        _ => fail(&format!("unknown format '{}'", format)),
+    }
+}
+
 fn main() -> Result<()> {
     let args: Vec<String> = env::args().collect();
 
@@ -36,6 +77,21 @@ fn main() -> Result<()> {
     } else {
         match args[1].as_str() {
             "--help" => help(),
+            "--from-tracery" => {
+                let content = match args.get(2) {
+                    Some(file) => fs::read_to_string(file)?,
+                    None => {
+                        let mut buffer = String::new();
+                        io::stdin().read_to_string(&mut buffer)?;
+                        buffer
+                    },
+                };
+                match tracery_to_json(&content) {
+                    Ok(json) => println!("{}", json),
+                    Err(e) => fail(&e.to_string()),
+                }
+                Ok(())
+            },
             "--regender" => {
                 if args.len() < 3 {
                     println!("Error: --regender takes a gender (m/f/n) as additional argument");
@@ -60,12 +116,59 @@ fn main() -> Result<()> {
                     Ok(())
                 }
             },
-            symbol => {
-                let mut buffer = String::new();
-                io::stdin().read_to_string(&mut buffer)?;
+            _ => {
+                let mut format: Option<String> = None;
+                let mut positional: Vec<&str> = vec![];
+                let mut args = args[1..].iter();
+                while let Some(arg) = args.next() {
+                    match arg.as_str() {
+                        "--format" => match args.next() {
+                            Some(f) => format = Some(f.to_lowercase()),
+                            None => fail("--format takes a format as additional argument"),
+                        },
+                        arg => positional.push(arg),
+                    }
+                }
+
+                // A single argument can either be a symbol or a file
+                let (symbol, file) = match positional.as_slice() {
+                    [] => (None, None),
+                    [arg] if Path::new(arg).is_file() => (None, Some(*arg)),
+                    [symbol] => (Some(*symbol), None),
+                    [symbol, file] => (Some(*symbol), Some(*file)),
+                    _ => help(),
+                };
+
+                let content = match file {
+                    Some(file) => {
+                        if format.is_none() {
+                            format = Path::new(file)
+                                .extension()
+                                .map(|e| e.to_string_lossy().to_lowercase())
+                                .filter(|e| ["json", "yaml", "yml", "toml", "ron"]
+                                        .contains(&e.as_str()));
+                        }
+                        fs::read_to_string(file)?
+                    },
+                    None => {
+                        let mut buffer = String::new();
+                        io::stdin().read_to_string(&mut buffer)?;
+                        buffer
+                    },
+                };
+
                 let mut generator = Generator::new();
-                generator.add_json(&buffer)?;
-                println!("{}", generator.instantiate(symbol)?);
+                add_grammar(&mut generator,
+                            &content,
+                            format.as_deref().unwrap_or("json"))?;
+                let symbol = match symbol {
+                    Some(symbol) => symbol.to_string(),
+                    None => match generator.metadata().entry {
+                        Some(ref entry) => entry.clone(),
+                        None => fail("no symbol given, and the grammar doesn't set an entry symbol"),
+                    },
+                };
+                println!("{}", generator.instantiate(&symbol)?);
                 Ok(())
             }
         }
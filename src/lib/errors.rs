@@ -4,6 +4,7 @@
 
 #![allow(missing_docs)]
 #![allow(deprecated)]
+#![allow(unexpected_cfgs)]
 use error_chain::*;
 
 error_chain! {
@@ -11,5 +12,10 @@ error_chain! {
         Io(::std::io::Error);
         Fmt(::std::fmt::Error);
         Json(serde_json::error::Error);
+        Yaml(serde_yaml::Error) #[cfg(feature = "yaml")];
+        Toml(toml::de::Error) #[cfg(feature = "toml")];
+        TomlSer(toml::ser::Error) #[cfg(feature = "toml")];
+        Ron(ron::error::SpannedError) #[cfg(feature = "ron")];
+        RonSer(ron::error::Error) #[cfg(feature = "ron")];
     }
 }
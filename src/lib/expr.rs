@@ -0,0 +1,325 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with
+// this file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Numeric expressions, such as `{2d6+1}`, `{1-6}` or `{gold * 2}`.
+
+use crate::errors::Result;
+
+use std::collections::HashMap;
+use std::iter::Peekable;
+use std::str::Chars;
+
+use error_chain::bail;
+use rand::distributions::Uniform;
+use rand::Rng;
+
+/// Maximum number of dice that can be rolled at once
+const MAX_DICE: i64 = 1000;
+
+/// A numeric expression
+#[derive(Debug, PartialEq)]
+pub(crate) enum Expr {
+    /// An integer, e.g. `42`
+    Number(i64),
+    /// Some dice, e.g. `2d6` (or `d6` for a single die)
+    Dice(i64, i64),
+    /// A random number in an inclusive range, e.g. `1-6`
+    Range(i64, i64),
+    /// The value of a symbol, which must be a number
+    Symbol(String),
+    /// `-expr`
+    Neg(Box<Expr>),
+    /// A binary operation, with the operator being one of `+`, `-`, `*` or `/`
+    Op(char, Box<Expr>, Box<Expr>),
+}
+
+/// A token of an expression
+#[derive(Debug, PartialEq)]
+enum Token {
+    Number(i64),
+    Dice(i64, i64),
+    Range(i64, i64),
+    Symbol(String),
+    Op(char),
+    Open,
+    Close,
+}
+
+/// Reads an integer whose first digit has already been read
+fn read_number(first: char, chars: &mut Peekable<Chars>) -> Result<i64> {
+    let mut s = first.to_string();
+    while let Some(&c) = chars.peek() {
+        if !c.is_ascii_digit() {
+            break;
+        }
+        s.push(c);
+        chars.next();
+    }
+    match s.parse() {
+        Ok(n) => Ok(n),
+        Err(_) => bail!("Number '{}' is too large", s),
+    }
+}
+
+/// Splits an expression into tokens
+fn tokenize(s: &str) -> Result<Vec<Token>> {
+    let mut tokens = vec![];
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => (),
+            '+' | '-' | '*' | '/' => tokens.push(Token::Op(c)),
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            c if c.is_ascii_digit() => {
+                let n = read_number(c, &mut chars)?;
+                // `NdM` is a dice roll, and `A-B` (without spaces) a range
+                match chars.peek() {
+                    Some('d') => {
+                        chars.next();
+                        match chars.next() {
+                            Some(c) if c.is_ascii_digit() => {
+                                tokens.push(Token::Dice(n, read_number(c, &mut chars)?))
+                            }
+                            _ => bail!("Invalid dice in expression '{}'", s),
+                        }
+                    }
+                    Some('-') => {
+                        let mut ahead = chars.clone();
+                        ahead.next();
+                        match ahead.next() {
+                            Some(c) if c.is_ascii_digit() => {
+                                chars.next();
+                                chars.next();
+                                tokens.push(Token::Range(n, read_number(c, &mut chars)?));
+                            }
+                            _ => tokens.push(Token::Number(n)),
+                        }
+                    }
+                    _ => tokens.push(Token::Number(n)),
+                }
+            }
+            c if c.is_alphanumeric() || c == '_' => {
+                let mut name = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if !c.is_alphanumeric() && c != '_' {
+                        break;
+                    }
+                    name.push(c);
+                    chars.next();
+                }
+                // `d6` is a single die
+                let sides = name.strip_prefix('d').filter(|n| n.chars().all(|c| c.is_ascii_digit()));
+                match sides {
+                    Some(sides) if !sides.is_empty() => match sides.parse() {
+                        Ok(sides) => tokens.push(Token::Dice(1, sides)),
+                        Err(_) => bail!("Number '{}' is too large", sides),
+                    },
+                    _ => tokens.push(Token::Symbol(name)),
+                }
+            }
+            c => bail!("Unexpected character '{}' in expression '{}'", c, s),
+        }
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent parser
+struct Parser<'a> {
+    tokens: Peekable<std::vec::IntoIter<Token>>,
+    source: &'a str,
+}
+
+impl<'a> Parser<'a> {
+    /// expr := term (('+' | '-') term)*
+    fn expr(&mut self) -> Result<Expr> {
+        let mut left = self.term()?;
+        while let Some(Token::Op(op)) = self.tokens.peek() {
+            let op = *op;
+            if op != '+' && op != '-' {
+                break;
+            }
+            self.tokens.next();
+            left = Expr::Op(op, Box::new(left), Box::new(self.term()?));
+        }
+        Ok(left)
+    }
+
+    /// term := factor (('*' | '/') factor)*
+    fn term(&mut self) -> Result<Expr> {
+        let mut left = self.factor()?;
+        while let Some(Token::Op(op)) = self.tokens.peek() {
+            let op = *op;
+            if op != '*' && op != '/' {
+                break;
+            }
+            self.tokens.next();
+            left = Expr::Op(op, Box::new(left), Box::new(self.factor()?));
+        }
+        Ok(left)
+    }
+
+    /// factor := '-' factor | '(' expr ')' | number | dice | range | symbol
+    fn factor(&mut self) -> Result<Expr> {
+        match self.tokens.next() {
+            Some(Token::Op('-')) => Ok(Expr::Neg(Box::new(self.factor()?))),
+            Some(Token::Open) => {
+                let e = self.expr()?;
+                match self.tokens.next() {
+                    Some(Token::Close) => Ok(e),
+                    _ => bail!("Missing ')' in expression '{}'", self.source),
+                }
+            }
+            Some(Token::Number(n)) => Ok(Expr::Number(n)),
+            Some(Token::Dice(n, m)) => Ok(Expr::Dice(n, m)),
+            Some(Token::Range(a, b)) => Ok(Expr::Range(a, b)),
+            Some(Token::Symbol(s)) => Ok(Expr::Symbol(s)),
+            _ => bail!("Invalid expression '{}'", self.source),
+        }
+    }
+}
+
+impl Expr {
+    /// Parses an expression
+    pub fn parse(s: &str) -> Result<Expr> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?.into_iter().peekable(),
+            source: s,
+        };
+        let e = parser.expr()?;
+        if parser.tokens.next().is_some() {
+            bail!("Invalid expression '{}'", s);
+        }
+        Ok(e)
+    }
+
+    /// Returns the symbols that are used by this expression
+    pub fn symbols(&self) -> Vec<&str> {
+        match self {
+            Expr::Symbol(s) => vec![s.as_str()],
+            Expr::Neg(e) => e.symbols(),
+            Expr::Op(_, a, b) => {
+                let mut v = a.symbols();
+                v.extend(b.symbols());
+                v
+            }
+            _ => vec![],
+        }
+    }
+
+    /// Evaluates the expression, given the values of the symbols it uses
+    pub fn eval<R: Rng>(&self, rng: &mut R, values: &HashMap<&str, i64>) -> Result<i64> {
+        Ok(match self {
+            Expr::Number(n) => *n,
+            Expr::Dice(n, m) => {
+                if *n > MAX_DICE {
+                    bail!("Can not roll more than {} dice at once", MAX_DICE);
+                }
+                if *m < 1 {
+                    bail!("Dice must have at least one side");
+                }
+                let die = Uniform::new_inclusive(1, *m);
+                let mut total: i64 = 0;
+                for _ in 0..*n {
+                    total = match total.checked_add(rng.sample(die)) {
+                        Some(total) => total,
+                        None => bail!("Overflow when rolling {}d{}", n, m),
+                    };
+                }
+                total
+            }
+            Expr::Range(a, b) => {
+                if a > b {
+                    bail!("Invalid range {}-{}", a, b);
+                }
+                rng.sample(Uniform::new_inclusive(*a, *b))
+            }
+            Expr::Symbol(s) => values[s.as_str()],
+            Expr::Neg(e) => {
+                let n = e.eval(rng, values)?;
+                match n.checked_neg() {
+                    Some(n) => n,
+                    None => bail!("Overflow when computing -({})", n),
+                }
+            }
+            Expr::Op(op, a, b) => {
+                let a = a.eval(rng, values)?;
+                let b = b.eval(rng, values)?;
+                let result = match op {
+                    '+' => a.checked_add(b),
+                    '-' => a.checked_sub(b),
+                    '*' => a.checked_mul(b),
+                    '/' => {
+                        if b == 0 {
+                            bail!("Division by zero");
+                        }
+                        a.checked_div(b)
+                    }
+                    _ => unreachable!(),
+                };
+                match result {
+                    Some(n) => n,
+                    None => bail!("Overflow when computing {} {} {}", a, op, b),
+                }
+            }
+        })
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////
+//                                    TESTS
+///////////////////////////////////////////////////////////////////////////////////////////
+
+#[test]
+fn parse() {
+    use Expr::*;
+    assert_eq!(Expr::parse("42").unwrap(), Number(42));
+    assert_eq!(Expr::parse("1-6").unwrap(), Range(1, 6));
+    assert_eq!(Expr::parse("1 - 6").unwrap(),
+               Op('-', Box::new(Number(1)), Box::new(Number(6))));
+    assert_eq!(Expr::parse("2d6+1").unwrap(),
+               Op('+', Box::new(Dice(2, 6)), Box::new(Number(1))));
+    assert_eq!(Expr::parse("d20").unwrap(), Dice(1, 20));
+    assert_eq!(Expr::parse("-(gold * 2)").unwrap(),
+               Neg(Box::new(Op('*', Box::new(Symbol("gold".into())), Box::new(Number(2))))));
+    assert!(Expr::parse("2 +").is_err());
+    assert!(Expr::parse("(2").is_err());
+    assert!(Expr::parse("2 3").is_err());
+    assert!(Expr::parse("2 % 3").is_err());
+}
+
+#[test]
+fn eval() {
+    let mut rng = rand::thread_rng();
+    let mut values = HashMap::new();
+    values.insert("gold", 10);
+    let eval = |s: &str, rng: &mut rand::rngs::ThreadRng| Expr::parse(s).unwrap().eval(rng, &values);
+    assert_eq!(eval("gold * 2 + 3", &mut rng).unwrap(), 23);
+    assert_eq!(eval("(1 + 2) * -3 / 2", &mut rng).unwrap(), -4);
+    for _ in 0..20 {
+        let n = eval("2d6", &mut rng).unwrap();
+        assert!((2..=12).contains(&n));
+        let n = eval("3-4", &mut rng).unwrap();
+        assert!(n == 3 || n == 4);
+    }
+    assert!(eval("1 / 0", &mut rng).is_err());
+    assert!(eval("6-1", &mut rng).is_err());
+    assert!(eval("5000d6", &mut rng).is_err());
+    assert!(eval("9223372036854775807 + 1", &mut rng).is_err());
+}
+
+#[test]
+fn eval_limits() {
+    let mut rng = rand::thread_rng();
+    let values = HashMap::new();
+    let eval = |s: &str, rng: &mut rand::rngs::ThreadRng| Expr::parse(s).unwrap().eval(rng, &values);
+    assert!(eval("1d9223372036854775807", &mut rng).unwrap() >= 1);
+    assert!(eval("1000d9223372036854775806", &mut rng).is_err());
+    // `0-9223372036854775807` is a range
+    assert!(eval("-(0-9223372036854775807-1)", &mut rng).unwrap() <= 1);
+    assert_eq!(eval("9223372036854775807-9223372036854775807", &mut rng).unwrap(),
+               9223372036854775807);
+    assert!(eval("-(0 - 9223372036854775807 - 1)", &mut rng).is_err());
+    assert_eq!(eval("-(0 - 9223372036854775807)", &mut rng).unwrap(), 9223372036854775807);
+}
@@ -3,38 +3,220 @@
 // this file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use crate::errors::Result;
+use crate::expr::Expr;
+use crate::grammar::{self, Alternative, Grammar, Marker, Metadata};
+use crate::language::{
+    self, English, French, GenericLanguage, German, Language, Portuguese, Spanish,
+};
+use crate::plural::{self, PluralCategory};
+use crate::pronouns::PronounSet;
+use crate::tracery;
 
 use std::borrow::Cow;
+use std::cell::Cell;
 use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
 use error_chain::bail;
 use lazy_static::lazy_static;
 use rand::prelude::*;
 use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
 
 /// Gender
 ///
 /// This is used to set the grammatical gender of an expression.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
 pub enum Gender {
     /// He
+    #[serde(rename = "m", alias = "M")]
     Male,
     /// She
+    #[serde(rename = "f", alias = "F")]
     Female,
     /// Neutral gender or when it isn't set
+    #[serde(rename = "n", alias = "N")]
     Neutral,
+    /// One of the gender classes declared by the grammar, by index (see
+    /// `Generator::set_gender_classes`)
+    #[serde(skip)]
+    Class(usize),
+}
+
+/// How gendered expressions are rendered when the gender is neutral (or isn't set), and
+/// the expression doesn't have a specific neutral form.
+///
+/// In the `"neutral_style"` field of the metadata, it is written in snake case, e.g.
+/// `"median_point"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NeutralStyle {
+    /// Depends on the language, e.g. "sorcier/sorcière"
+    #[default]
+    Slash,
+    /// Both forms joined with the language's word for "or", e.g. "sorcier ou sorcière"
+    Doublet,
+    /// Median point, e.g. "sorcier·ère"
+    MedianPoint,
+    /// Feminine ending between parentheses, e.g. "sorcier(ère)"
+    Parenthetical,
+    /// Masculine form, e.g. "sorcier"
+    Masculine,
+    /// Instantiation fails with an error
+    Error,
+}
+
+/// How the gender of a group is computed from the genders of its members, for expressions
+/// that depend on several symbols, e.g. `ils/elles/iels[hero,sidekick]`.
+///
+/// In all cases, if all the members are neutral (or don't have a gender), so is the group.
+/// With custom gender classes, "masculine" stands for the first class.
+///
+/// In the `"group_rule"` field of the metadata, it is written in snake case, e.g.
+/// `"neutral_if_mixed"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupRule {
+    /// The group is masculine
+    Masculine,
+    /// The group is feminine if all its members are, and masculine otherwise
+    #[default]
+    FeminineIfAll,
+    /// The group has the gender of its members if they all have the same, and is
+    /// neutral otherwise
+    NeutralIfMixed,
 }
 
 #[derive(Debug, Clone)]
 struct Replaced {
     pub content: String,
     pub gender: Gender,
+    /// Gender of the person or thing it refers to, if it differs from its grammatical
+    /// gender
+    pub referent: Option<Gender>,
+    /// Name of the pronoun set, if it has one
+    pub pronouns: Option<String>,
+    /// Whether it was set explicitly with `{set ...}`, in which case it isn't forgotten
+    /// by `{{symbol}}`
+    pub set: bool,
+}
+
+impl Gender {
+    /// Parses a gender marker, as used in `[m]`, `[f]` or `[n]`
+    fn parse(s: &str) -> Result<Gender> {
+        match s {
+            "m" | "M" => Ok(Gender::Male),
+            "f" | "F" => Ok(Gender::Female),
+            "n" | "N" => Ok(Gender::Neutral),
+            _ => bail!("Invalid gender '{}': must be 'm', 'f' or 'n'", s),
+        }
+    }
+
+    /// Parses a gender marker, which is one of `classes` if there are custom gender
+    /// classes
+    pub(crate) fn parse_class(s: &str, classes: &[String]) -> Result<Gender> {
+        if classes.is_empty() {
+            return Gender::parse(s);
+        }
+        match classes.iter().position(|c| c == s) {
+            Some(i) => Ok(Gender::Class(i)),
+            None => bail!("Invalid gender '{}': must be one of {}", s, classes.join(", ")),
+        }
+    }
+
+    /// Returns the marker of a gender, given the custom gender classes
+    pub(crate) fn marker(self, classes: &[String]) -> String {
+        match self {
+            Gender::Male => "m".to_string(),
+            Gender::Female => "f".to_string(),
+            Gender::Neutral => "n".to_string(),
+            Gender::Class(i) => match classes.get(i) {
+                Some(class) => class.clone(),
+                None => i.to_string(),
+            },
+        }
+    }
+
+    /// Returns the index of the form used for this gender in `form1/form2/...`
+    /// expressions, or `None` if it is neutral.
+    ///
+    /// `Male` and `Female` use the same forms as the first two gender classes.
+    fn slot(self) -> Option<usize> {
+        match self {
+            Gender::Male => Some(0),
+            Gender::Female => Some(1),
+            Gender::Neutral => None,
+            Gender::Class(i) => Some(i),
+        }
+    }
+}
+
+/// Similar to `Regex::replace_all`, except that the replacement closure can fail, in which
+/// case the error is returned.
+fn try_replace_all<F>(re: &Regex, s: &str, mut f: F) -> Result<String>
+where
+    F: FnMut(&Captures) -> Result<String>,
+{
+    let mut result = String::with_capacity(s.len());
+    let mut last = 0;
+    for caps in re.captures_iter(s) {
+        let m = caps.get(0).unwrap();
+        result.push_str(&s[last..m.start()]);
+        result.push_str(&f(&caps)?);
+        last = m.end();
+    }
+    result.push_str(&s[last..]);
+    Ok(result)
+}
+
+/// One of the possible contents of a replacement
+#[derive(Debug)]
+struct Variant {
+    pub content: String,
+    pub gender: Option<Gender>,
+    pub referent: Option<Gender>,
+    pub weight: u32,
+    pub tags: Vec<String>,
+}
+
+impl From<String> for Variant {
+    fn from(content: String) -> Variant {
+        Variant {
+            content,
+            gender: None,
+            referent: None,
+            weight: 1,
+            tags: vec![],
+        }
+    }
 }
 
 #[derive(Debug)]
 struct Replacement {
+    /// Dependencies of the symbol, e.g. `hero`, or `hero,#n` to also depend on a count
     pub gender_dependency: Option<String>,
-    pub content: Vec<String>,
+    /// Parameters of the symbol, if it is declared as `symbol(param1, param2)`
+    pub params: Vec<String>,
+    pub content: Vec<Variant>,
+}
+
+/// The dependencies of a gendered expression, e.g. `[hero,#n]`
+#[derive(Debug, Default)]
+struct Dependencies<'a> {
+    /// Symbols whose gender is used: if there are several ones, the gender of the group.
+    /// The boolean is true if the referent gender is used (`hero:ref`) instead of the
+    /// grammatical one
+    pub gender: Vec<(&'a str, bool)>,
+    /// Symbol whose (numeric) value is used to pick a plural form
+    pub count: Option<&'a str>,
+}
+
+/// A reference to a symbol, of the form `symbol(arg1, arg2)#tag1#tag2`
+#[derive(Debug)]
+struct Reference<'a> {
+    pub symbol: &'a str,
+    pub args: Vec<&'a str>,
+    pub tags: Vec<&'a str>,
 }
 
 /// Generator. Main structure of this library.
@@ -49,15 +231,321 @@ struct Replacement {
 pub struct Generator {
     replaced: HashMap<String, Replaced>,
     replacements: HashMap<String, Replacement>,
+    metadata: Metadata,
+    /// Language profiles that can be used, by code
+    languages: HashMap<String, Arc<dyn Language>>,
+    /// Language profile set with `set_language`, if any
+    language_override: Option<Arc<dyn Language>>,
+    /// Language profile in use
+    language: Arc<dyn Language>,
+}
+
+impl Default for Generator {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Generator {
     /// Creates a new, empty Generator.
     pub fn new() -> Self {
+        let mut languages: HashMap<String, Arc<dyn Language>> = HashMap::new();
+        languages.insert("en".into(), Arc::new(English));
+        languages.insert("fr".into(), Arc::new(French));
+        languages.insert("es".into(), Arc::new(Spanish::default()));
+        languages.insert("pt".into(), Arc::new(Portuguese::default()));
+        languages.insert("de".into(), Arc::new(German::default()));
         Generator {
             replacements: HashMap::new(),
             replaced: HashMap::new(),
+            metadata: Metadata::default(),
+            languages,
+            language_override: None,
+            language: Arc::new(English),
+        }
+    }
+
+    /// Returns the metadata of the generator, as set by the `"$meta"` key of the grammars
+    /// that were added to it.
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Sets the metadata of the generator.
+    pub fn set_metadata(&mut self, metadata: Metadata) {
+        self.metadata = metadata;
+        self.update_language();
+    }
+
+    /// Returns the language profile in use.
+    ///
+    /// Unless one was set with `set_language`, it is the one whose code matches the
+    /// language of the metadata (English by default). Languages that genere doesn't know,
+    /// and which haven't been registered with `register_language`, use a
+    /// `GenericLanguage` profile.
+    pub fn language(&self) -> &dyn Language {
+        &*self.language
+    }
+
+    /// Sets the language profile to use, regardless of the language of the metadata.
+    pub fn set_language<L: Language + 'static>(&mut self, language: L) {
+        self.language_override = Some(Arc::new(language));
+        self.update_language();
+    }
+
+    /// Registers a language profile, which is used if the language of the metadata
+    /// matches its code.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use genere::{Generator, Language};
+    /// struct Klingon;
+    /// impl Language for Klingon {
+    ///     fn code(&self) -> &str { "tlh" }
+    ///     fn neutral(&self, m: &str, f: &str) -> String { format!("{} or {}", m, f) }
+    /// }
+    ///
+    /// let mut gen = Generator::new();
+    /// gen.register_language(Klingon);
+    /// gen.add_json(r#"{"$meta": {"language": "tlh"}, "main": ["he/she"]}"#).unwrap();
+    /// assert_eq!(&gen.instantiate("main").unwrap(), "he or she");
+    /// ```
+    pub fn register_language<L: Language + 'static>(&mut self, language: L) {
+        self.languages
+            .insert(language.code().to_lowercase(), Arc::new(language));
+        self.update_language();
+    }
+
+    /// Sets how gendered expressions are rendered when the gender is neutral (or isn't
+    /// set), and they don't have a specific neutral form.
+    ///
+    /// This can also be set with the `"neutral_style"` field of a grammar's metadata.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use genere::{Generator, NeutralStyle};
+    /// let mut gen = Generator::new();
+    /// gen.add("main", &["un·e sorci·er·ère, il/elle"]).unwrap();
+    /// gen.set_neutral_style(NeutralStyle::Parenthetical);
+    /// assert_eq!(&gen.instantiate("main").unwrap(), "un(e) sorcier(ère), il (elle)");
+    /// ```
+    pub fn set_neutral_style(&mut self, style: NeutralStyle) {
+        self.metadata.neutral_style = Some(style);
+    }
+
+    /// Sets the syntaxes for gendered expressions that are recognised, replacing the ones
+    /// of the `"markers"` field of the metadata. By default, they are `Inclusive`,
+    /// `MedianPoint` and `Slash`.
+    ///
+    /// In strict mode, with only `Marker::Explicit`, gender alternation only happens in
+    /// explicit expressions such as `<il|elle|iel>`, so that e.g. dates and URLs are
+    /// left untouched.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use genere::{Generator, Marker};
+    /// let mut gen = Generator::new();
+    /// gen.add("hero", &["Léa[f]"]).unwrap();
+    /// gen.add("main[hero]", &["Le 12/04, <il|elle> a vu le site a.fr/b."]).unwrap();
+    /// gen.set_markers(&[Marker::Explicit]);
+    /// assert_eq!(&gen.instantiate("main").unwrap(), "Le 12/04, elle a vu le site a.fr/b.");
+    /// ```
+    pub fn set_markers(&mut self, markers: &[Marker]) {
+        self.metadata.markers = Some(markers.to_vec());
+    }
+
+    /// Returns whether a syntax for gendered expressions is recognised
+    fn has_marker(&self, marker: Marker) -> bool {
+        match &self.metadata.markers {
+            Some(markers) => markers.contains(&marker),
+            None => grammar::DEFAULT_MARKERS.contains(&marker),
+        }
+    }
+
+    /// Sets how the gender of a group is computed, for expressions that depend on several
+    /// symbols.
+    ///
+    /// This can also be set with the `"group_rule"` field of a grammar's metadata.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use genere::{Generator, GroupRule};
+    /// let mut gen = Generator::new();
+    /// gen.add("hero", &["Jean[m]"]).unwrap();
+    /// gen.add("sidekick", &["Marie[f]"]).unwrap();
+    /// gen.add("main", &["{hero} et {sidekick} : ils/elles/iels[hero,sidekick] sont là."]).unwrap();
+    /// assert_eq!(&gen.instantiate("main").unwrap(), "Jean et Marie : ils sont là.");
+    /// gen.set_group_rule(GroupRule::NeutralIfMixed);
+    /// assert_eq!(&gen.instantiate("main").unwrap(), "Jean et Marie : iels sont là.");
+    /// ```
+    pub fn set_group_rule(&mut self, rule: GroupRule) {
+        self.metadata.group_rule = Some(rule);
+    }
+
+    /// Enables or disables the capitalization of the first letter of each sentence, which
+    /// can also be set with `"capitalize_sentences": true` in the metadata.
+    ///
+    /// Sentences end with ".", "!", "?" or "…", unless they are escaped, e.g. "etc~.". Words
+    /// with uppercase letters after the first one, e.g. "iPhone", are left untouched, as
+    /// well as words preceded by `~=`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use genere::Generator;
+    /// let mut gen = Generator::new();
+    /// gen.add("hero", &["le chevalier[m]"]).unwrap();
+    /// gen.set_capitalize_sentences(true);
+    /// let s = gen.msg("{hero} arrive. il/elle[hero] lit etc~. ! ~=bell hooks ? oui.", &[]);
+    /// assert_eq!(&s.unwrap(), "Le chevalier arrive. Il lit etc. ! bell hooks ? Oui.");
+    /// ```
+    pub fn set_capitalize_sentences(&mut self, enabled: bool) {
+        self.metadata.capitalize_sentences = Some(enabled);
+    }
+
+    /// Enables or disables typographic conventions, which can also be set with
+    /// `"typography": true` in the metadata: straight quotes are replaced by the language's
+    /// quotation marks, runs of spaces are collapsed, spaces before commas are removed, and
+    /// in French, narrow no-break spaces are put before ";", ":", "!" and "?" and inside « ».
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use genere::Generator;
+    /// let mut gen = Generator::new();
+    /// gen.add("adjective", &[""]).unwrap();
+    /// gen.set_typography(true);
+    /// assert_eq!(&gen.msg(r#"A {adjective} "sword" , isn't it?"#, &[]).unwrap(),
+    ///            "A “sword”, isn’t it?");
+    /// ```
+    pub fn set_typography(&mut self, enabled: bool) {
+        self.metadata.typography = Some(enabled);
+    }
+
+    /// Enables or disables contractions, such as French elision ("le épée" becomes
+    /// "l'épée") and contractions ("de le" becomes "du"), which are disabled by default.
+    ///
+    /// This can also be set with the `"contractions"` field of a grammar's metadata.
+    /// Only the forms of gendered expressions, articles inserted by `{a:symbol}` and words
+    /// right before a `{symbol}` are contracted, so ordinary text is left untouched.
+    /// Elision can be prevented for a word starting with an aspirated h by escaping it
+    /// (`~h`), and contractions by using an escaped space (`de~ le`).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use genere::Generator;
+    /// let mut gen = Generator::new();
+    /// gen.add_json(r#"{"$meta": {"language": "fr"}, "arme": ["épée[f]"], "héros": ["~héros"]}"#)
+    ///     .unwrap();
+    /// gen.set_contractions(true);
+    /// assert_eq!(&gen.msg("le/la[arme] {arme} de le {héros}", &[]).unwrap(),
+    ///            "l'épée du héros");
+    /// assert_eq!(&gen.msg("de le faire", &[]).unwrap(), "de le faire");
+    /// ```
+    pub fn set_contractions(&mut self, enabled: bool) {
+        self.metadata.contractions = Some(enabled);
+    }
+
+    /// Renders the masculine and feminine forms of an expression for a neutral gender,
+    /// according to the neutral style.
+    ///
+    /// Special characters of the result are escaped, so that it isn't adapted again.
+    fn neutral(&self, masculine: &str, feminine: &str) -> Result<String> {
+        if masculine == feminine {
+            return Ok(masculine.to_string());
+        }
+        let common: usize = masculine
+            .chars()
+            .zip(feminine.chars())
+            .take_while(|(m, f)| m == f)
+            .map(|(m, _)| m.len_utf8())
+            .sum();
+        let ending = &feminine[common..];
+        let s = match self.metadata.neutral_style.unwrap_or_default() {
+            NeutralStyle::Slash => self.language.neutral(masculine, feminine),
+            NeutralStyle::Doublet => self.language.doublet(masculine, feminine),
+            NeutralStyle::Masculine => masculine.to_string(),
+            NeutralStyle::MedianPoint if common == 0 => format!("{}·{}", masculine, feminine),
+            NeutralStyle::MedianPoint => format!("{}·{}", masculine, ending),
+            NeutralStyle::Parenthetical if common == 0 => format!("{} ({})", masculine, feminine),
+            NeutralStyle::Parenthetical => format!("{}({})", masculine, ending),
+            NeutralStyle::Error => bail!(
+                "Gender is neutral or not set, and '{}/{}' has no neutral form",
+                masculine,
+                feminine
+            ),
+        };
+        Ok(Self::protect(&s))
+    }
+
+    /// Similar to `neutral`, but for phrases that only differ by their last words, e.g.
+    /// "vingt et un" and "vingt et une"
+    fn neutral_phrase(&self, masculine: &str, feminine: &str) -> Result<String> {
+        let common: usize = masculine
+            .chars()
+            .zip(feminine.chars())
+            .take_while(|(m, f)| m == f)
+            .map(|(m, _)| m.len_utf8())
+            .sum();
+        let start = match masculine[..common].rfind([' ', '-']) {
+            Some(i) => i + 1,
+            None => 0,
+        };
+        Ok(format!(
+            "{}{}",
+            &masculine[..start],
+            self.neutral(&masculine[start..], &feminine[start..])?
+        ))
+    }
+
+    /// Marks a word as one that contractions apply to, if they are enabled, so that they
+    /// don't affect ordinary text
+    fn contractible(&self, s: String) -> String {
+        if self.metadata.contractions == Some(true) {
+            format!("~<contract>{}", s)
+        } else {
+            s
+        }
+    }
+
+    /// Escapes the characters that have a special meaning in genere
+    fn protect(s: &str) -> String {
+        let mut result = String::with_capacity(s.len());
+        for c in s.chars() {
+            match c {
+                '/' => result.push_str("~<slash>"),
+                '·' => result.push_str("~<median>"),
+                '(' => result.push_str("~<leftparen>"),
+                ')' => result.push_str("~<rightparen>"),
+                '[' => result.push_str("~<leftsquare>"),
+                ']' => result.push_str("~<rightsquare>"),
+                '{' => result.push_str("~<leftcurly>"),
+                '}' => result.push_str("~<rightcurly>"),
+                c => result.push(c),
+            }
         }
+        result
+    }
+
+    /// Picks the language profile to use
+    fn update_language(&mut self) {
+        self.language = match self.language_override {
+            Some(ref language) => language.clone(),
+            None => {
+                let code = self.metadata.language.as_deref().unwrap_or("en");
+                let lower = code.to_lowercase();
+                let short = lower.split(['-', '_']).next().unwrap_or("");
+                match self.languages.get(&lower).or_else(|| self.languages.get(short)) {
+                    Some(language) => language.clone(),
+                    None => Arc::new(GenericLanguage::new(code)),
+                }
+            }
+        };
     }
 
     /// Preprocess a string to replaced escaped characters that characters that won't
@@ -77,7 +565,18 @@ impl Generator {
                 r"}" => Cow::Borrowed(r"~<rightcurly>"),
                 r"/" => Cow::Borrowed(r"~<slash>"),
                 r"·" => Cow::Borrowed(r"~<median>"),
-                n => Cow::Owned(format!("{}", n)),
+                r"(" => Cow::Borrowed(r"~<leftparen>"),
+                r")" => Cow::Borrowed(r"~<rightparen>"),
+                r"<" => Cow::Borrowed(r"~<lessthan>"),
+                r"|" => Cow::Borrowed(r"~<pipe>"),
+                r"." => Cow::Borrowed(r"~<period>"),
+                r"=" => Cow::Borrowed(r"~<keepcase>"),
+                r"@" => Cow::Borrowed(r"~<at>"),
+                r"*" => Cow::Borrowed(r"~<star>"),
+                r":" => Cow::Borrowed(r"~<colon>"),
+                // An aspirated h, which prevents elision
+                h @ ("h" | "H") => Cow::Owned(format!("~<aspirated>{}", h)),
+                n => Cow::Owned(n.to_string()),
             });
             new_s.into_owned()
         } else {
@@ -85,6 +584,50 @@ impl Generator {
         }
     }
 
+    /// Applies the final passes to a generated text: contractions and capitalization of
+    /// sentences, if they are enabled, `post_process`, and typography, if it is enabled
+    fn finish(&self, s: &str) -> String {
+        let s = if self.metadata.contractions == Some(true) {
+            self.language.contract(s)
+        } else {
+            s.to_string()
+        };
+        let s = if self.metadata.capitalize_sentences == Some(true) {
+            self.capitalize_sentences(&s)
+        } else {
+            s
+        };
+        let s = Self::post_process(s);
+        if self.metadata.typography == Some(true) {
+            self.language.typography(&s)
+        } else {
+            s
+        }
+    }
+
+    /// Capitalizes the first word of the text and of each sentence, unless it has uppercase
+    /// letters after the first one (e.g. "iPhone") or is escaped with `~=`
+    fn capitalize_sentences(&self, s: &str) -> String {
+        lazy_static! {
+            static ref RE: Regex =
+                Regex::new(concat!(
+                r"(^|[.!?…][^\s\w~]*\s+)([^\w\s~]*\s*)",
+                r"((?:~<(?:aspirated|contract)>)*)(\w+)"
+            ))
+            .unwrap();
+        }
+
+        RE.replace_all(s, |caps: &Captures| {
+            let word = &caps[4];
+            if word.chars().skip(1).any(char::is_uppercase) {
+                caps[0].to_string()
+            } else {
+                format!("{}{}{}{}", &caps[1], &caps[2], &caps[3], self.language.capitalize(word))
+            }
+        })
+        .into_owned()
+    }
+
     /// Prost-process a string to replace escape characters with expected ones
     fn post_process(s: String) -> String {
         lazy_static! {
@@ -101,6 +644,17 @@ impl Generator {
                 "rightcurly" => r"}",
                 "slash" => "/",
                 "median" => "·",
+                "leftparen" => "(",
+                "rightparen" => ")",
+                "lessthan" => "<",
+                "pipe" => "|",
+                "period" => ".",
+                "at" => "@",
+                "star" => "*",
+                "colon" => ":",
+                "keepcase" => "",
+                "aspirated" => "",
+                "contract" => "",
                 _ => unreachable!(),
             });
             new_s.into_owned()
@@ -109,12 +663,250 @@ impl Generator {
         }
     }
 
+    /// Reverts `pre_process`, escaping special characters with `~` again.
+    fn escape(s: &str) -> String {
+        lazy_static! {
+            static ref RE: Regex = Regex::new(r"~<(\w+)>").unwrap();
+        }
+
+        RE.replace_all(s, |caps: &Captures| match &caps[1] {
+            "space" => "~ ",
+            "tilde" => "~~",
+            "leftsquare" => "~[",
+            "rightsquare" => "~]",
+            "leftcurly" => "~{",
+            "rightcurly" => "~}",
+            "slash" => "~/",
+            "median" => "~·",
+            "leftparen" => "~(",
+            "rightparen" => "~)",
+            "lessthan" => "~<",
+            "pipe" => "~|",
+            "period" => "~.",
+            "at" => "~@",
+            "star" => "~*",
+            "colon" => "~:",
+            "keepcase" => "~=",
+            "aspirated" => "~",
+            "contract" => "",
+            _ => unreachable!(),
+        })
+        .into_owned()
+    }
+
     /// Adds a replacement grammar using JSON format.
+    ///
+    /// Each symbol can either be given as a list of strings, or as an object
+    /// with a `depends_on` field, containing the symbol it takes its gender from, and an
+    /// `alternatives` field. Similarly, each alternative can either be a string or an
+    /// object with a `text` field and optional `gender`, `weight` and `tags` fields:
+    ///
+    /// ```
+    /// # use genere::Generator;
+    /// let json = r#"
+    /// {
+    ///     "weapon": [{"text": "épée", "gender": "f", "weight": 3, "tags": ["sharp"]},
+    ///                "gourdin[m]"],
+    ///     "main": {"depends_on": "weapon",
+    ///              "alternatives": ["Un·e {weapon}"]}
+    /// }"#;
+    /// let mut gen = Generator::new();
+    /// gen.add_json(json).unwrap();
+    /// # let s = gen.instantiate("main").unwrap();
+    /// # assert!(&s == "Une épée" || &s == "Un gourdin");
+    /// ```
     pub fn add_json(&mut self, json: &str) -> Result<()> {
-        let map: HashMap<String, Vec<String>> = serde_json::from_str(json)?;
+        let grammar: Grammar = serde_json::from_str(json)?;
+        self.add_grammar(grammar)
+    }
+
+    /// Adds a replacement grammar using YAML format.
+    ///
+    /// The structure is the same as for `add_json`. Requires the `yaml` feature.
+    #[cfg(feature = "yaml")]
+    pub fn add_yaml(&mut self, yaml: &str) -> Result<()> {
+        let grammar: Grammar = serde_yaml::from_str(yaml)?;
+        self.add_grammar(grammar)
+    }
+
+    /// Adds a replacement grammar using TOML format.
+    ///
+    /// The structure is the same as for `add_json`. Requires the `toml` feature.
+    #[cfg(feature = "toml")]
+    pub fn add_toml(&mut self, toml: &str) -> Result<()> {
+        let grammar: Grammar = toml::from_str(toml)?;
+        self.add_grammar(grammar)
+    }
+
+    /// Adds a replacement grammar using RON format.
+    ///
+    /// The structure is the same as for `add_json`. Requires the `ron` feature.
+    #[cfg(feature = "ron")]
+    pub fn add_ron(&mut self, ron: &str) -> Result<()> {
+        let grammar: Grammar = ron::from_str(ron)?;
+        self.add_grammar(grammar)
+    }
+
+    /// Adds a [Tracery](http://tracery.io/) grammar, in JSON format.
+    ///
+    /// The grammar is translated into genere syntax (see `tracery_to_json`). If some
+    /// elements can't be translated, an error listing all of them is returned and no
+    /// symbol is added.
+    pub fn add_tracery_json(&mut self, json: &str) -> Result<()> {
+        let translation = tracery::translate(json)?;
+        if self.metadata.entry.is_none() {
+            self.metadata.entry = translation.entry;
+        }
+        for (symbol, content) in translation.symbols {
+            self.add_move(symbol, content)?;
+        }
+        Ok(())
+    }
+
+    /// Exports the symbols (and metadata) of the generator in JSON format.
+    ///
+    /// The result can be loaded again with `add_json`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use genere::Generator;
+    /// let mut gen = Generator::new();
+    /// gen.add("hero", &["John[m]", "Joan[f]"]).unwrap();
+    /// gen.add("main[hero]", &["{hero}: he/she~/they"]).unwrap();
+    /// let json = gen.to_json().unwrap();
+    ///
+    /// let mut gen2 = Generator::new();
+    /// gen2.add_json(&json).unwrap();
+    /// assert_eq!(gen2.to_json().unwrap(), json);
+    /// ```
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(&self.to_grammar())?)
+    }
 
-        for (symbol, content) in map {
-            self.add_move(symbol.to_lowercase(), content)?;
+    /// Exports the symbols (and metadata) of the generator in YAML format.
+    ///
+    /// Requires the `yaml` feature.
+    #[cfg(feature = "yaml")]
+    pub fn to_yaml(&self) -> Result<String> {
+        Ok(serde_yaml::to_string(&self.to_grammar())?)
+    }
+
+    /// Exports the symbols (and metadata) of the generator in TOML format.
+    ///
+    /// Requires the `toml` feature.
+    #[cfg(feature = "toml")]
+    pub fn to_toml(&self) -> Result<String> {
+        Ok(toml::to_string(&self.to_grammar())?)
+    }
+
+    /// Exports the symbols (and metadata) of the generator in RON format.
+    ///
+    /// Requires the `ron` feature.
+    #[cfg(feature = "ron")]
+    pub fn to_ron(&self) -> Result<String> {
+        Ok(ron::ser::to_string_pretty(
+            &self.to_grammar(),
+            ron::ser::PrettyConfig::default(),
+        )?)
+    }
+
+    /// Converts the symbols and metadata of the generator to a grammar
+    fn to_grammar(&self) -> Grammar {
+        let mut grammar = Grammar::default();
+        if self.metadata != Metadata::default() {
+            grammar.meta = Some(self.metadata.clone());
+        }
+        for (symbol, replacement) in &self.replacements {
+            let mut name = Self::escape(symbol);
+            if !replacement.params.is_empty() {
+                name = format!("{}({})", name, replacement.params.join(", "));
+            }
+            if let Some(ref dependency) = replacement.gender_dependency {
+                name = format!("{}[{}]", name, dependency);
+            }
+            let alternatives = replacement
+                .content
+                .iter()
+                .map(|v| {
+                    let text = Self::escape(&v.content);
+                    if v.gender.is_none() && v.referent.is_none() && v.weight == 1 && v.tags.is_empty() {
+                        Alternative::Compact(text)
+                    } else {
+                        Alternative::Full {
+                            text,
+                            gender: v.gender.map(|g| self.gender_marker(g)),
+                            referent: v.referent.map(|g| self.gender_marker(g)),
+                            weight: if v.weight == 1 { None } else { Some(v.weight) },
+                            tags: v.tags.clone(),
+                        }
+                    }
+                })
+                .collect();
+            grammar
+                .symbols
+                .insert(name, grammar::Symbol::Compact(alternatives));
+        }
+        grammar
+    }
+
+    /// Adds all the symbols of a grammar
+    fn add_grammar(&mut self, grammar: Grammar) -> Result<()> {
+        if let Some(meta) = grammar.meta {
+            self.metadata.merge(meta);
+            self.update_language();
+        }
+        for (symbol, definition) in grammar.symbols {
+            if symbol.starts_with('$') {
+                bail!("Unknown reserved key '{}'", symbol);
+            }
+            let symbol = symbol.to_lowercase();
+            let (symbol, alternatives) = match definition {
+                grammar::Symbol::Compact(alternatives) => (symbol, alternatives),
+                grammar::Symbol::Full {
+                    depends_on: Some(dependency),
+                    alternatives,
+                } => {
+                    if symbol.contains('[') {
+                        bail!(
+                            "Symbol '{}' can not both declare a dependency in its name and with 'depends_on'",
+                            symbol
+                        );
+                    }
+                    (format!("{}[{}]", symbol, dependency), alternatives)
+                }
+                grammar::Symbol::Full {
+                    depends_on: None,
+                    alternatives,
+                } => (symbol, alternatives),
+            };
+
+            let mut variants = Vec::with_capacity(alternatives.len());
+            for alternative in alternatives {
+                variants.push(match alternative {
+                    Alternative::Compact(text) => Variant::from(text),
+                    Alternative::Full {
+                        text,
+                        gender,
+                        referent,
+                        weight,
+                        tags,
+                    } => Variant {
+                        content: text,
+                        gender: match gender {
+                            Some(gender) => Some(self.parse_gender(&gender)?),
+                            None => None,
+                        },
+                        referent: match referent {
+                            Some(referent) => Some(self.parse_gender(&referent)?),
+                            None => None,
+                        },
+                        weight: weight.unwrap_or(1),
+                        tags,
+                    },
+                });
+            }
+            self.add_variants(symbol, variants)?;
         }
         Ok(())
     }
@@ -124,10 +916,10 @@ impl Generator {
     /// # Arguments
     ///
     /// * `symbol`: the name that will be used to accessed the content. It is converted to
-    /// lowercase before being added to the `Generator`.
+    ///   lowercase before being added to the `Generator`.
     /// * `content`: a list of possible replacements for the symbol, that will be chosen
-    /// randomly when instantiated. Note that it can contain special marking to refer to
-    /// other symbol, gender replacements and so on.
+    ///   randomly when instantiated. Note that it can contain special marking to refer to
+    ///   other symbol, gender replacements and so on.
     pub fn add(&mut self, symbol: &str, content: &[&str]) -> Result<()> {
         let symbol: String = symbol.to_lowercase();
 
@@ -139,38 +931,53 @@ impl Generator {
     }
 
     /// Similar to `add`, but consume the arguments instead of taking a reference.
-    pub fn add_move(&mut self, mut symbol: String, mut content: Vec<String>) -> Result<()> {
+    pub fn add_move(&mut self, symbol: String, content: Vec<String>) -> Result<()> {
+        self.add_variants(symbol, content.into_iter().map(Variant::from).collect())
+    }
+
+    /// Adds a symbol and all its possible variants
+    fn add_variants(&mut self, mut symbol: String, mut content: Vec<Variant>) -> Result<()> {
         lazy_static! {
-            static ref RE: Regex = Regex::new(r"(.*)\[(\w*)\]").unwrap();
+            static ref RE: Regex = Regex::new(r"(.*)\[([\w,#:]*)\]").unwrap();
+            static ref RE_PARAMS: Regex =
+                Regex::new(r"^(.*)\(\s*(\w+(?:\s*,\s*\w+)*)?\s*\)$").unwrap();
         }
 
         symbol = Self::pre_process(symbol);
-        for i in 0..content.len() {
-            let c = std::mem::replace(&mut content[i], String::new());
-            content[i] = Self::pre_process(c);
+        for v in content.iter_mut() {
+            v.content = Self::pre_process(std::mem::take(&mut v.content));
         }
 
         let cap = RE.captures(&symbol);
-        let (symbol, replacement) = if let Some(cap) = cap {
-            let symbol = cap[1].into();
-            (
-                symbol,
-                Replacement {
-                    gender_dependency: Some(cap[2].into()),
-                    content: content,
-                },
-            )
+        let (mut symbol, gender_dependency) = if let Some(cap) = cap {
+            (cap[1].to_string(), Some(cap[2].to_string()))
         } else {
-            (
-                symbol,
-                Replacement {
-                    gender_dependency: None,
-                    content: content,
-                },
-            )
+            (symbol, None)
         };
 
-        self.replacements.insert(symbol, replacement);
+        // Parameters, if the symbol is declared as `symbol(param1, param2)`
+        let mut params: Vec<String> = vec![];
+        if let Some(cap) = RE_PARAMS.captures(&symbol) {
+            if let Some(m) = cap.get(2) {
+                for param in m.as_str().split(',') {
+                    let param = param.trim().to_lowercase();
+                    if params.contains(&param) {
+                        bail!("Parameter '{}' is declared twice in '{}'", param, symbol);
+                    }
+                    params.push(param);
+                }
+            }
+            symbol = cap[1].to_string();
+        }
+
+        self.replacements.insert(
+            symbol,
+            Replacement {
+                gender_dependency,
+                params,
+                content,
+            },
+        );
         Ok(())
     }
 
@@ -179,37 +986,314 @@ impl Generator {
         self.replaced.insert(
             symbol.into(),
             Replaced {
-                gender: gender,
+                gender,
                 content: String::new(),
+                referent: None,
+                pronouns: None,
+                set: false,
             },
         );
     }
 
-    fn get_gender<R: Rng>(
-        &self,
-        symbol: &str,
-        replaced: &mut HashMap<String, Replaced>,
-        rng: &mut R,
-        stack: &mut HashSet<String>,
-    ) -> Result<Gender> {
-        if !replaced.contains_key(symbol) {
-            self.instantiate_util(symbol, replaced, rng, stack)?;
-        }
-        match replaced.get(symbol) {
-            Some(replaced) => Ok(replaced.gender),
-            None => bail!(
-                "Some symbol needs a gender to be specified by {} but it doesn't specify one",
-                symbol
-            ),
+    /// Declares the gender classes used by the grammar, replacing the built-in `m`, `f`
+    /// and `n` genders.
+    ///
+    /// Gender markers are then the names of these classes, and `form1/form2/...`
+    /// expressions must give a form for each class, in the same order, and optionally one
+    /// more for when the gender isn't set. Gender classes can also be declared in the
+    /// `"genders"` field of a grammar's metadata.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use genere::Generator;
+    /// let mut gen = Generator::new();
+    /// gen.set_gender_classes(&["m", "f", "nt"]);
+    /// gen.add("object", &["Buch[nt]"]).unwrap();
+    /// gen.add("main", &["ein/eine/ein[object] {object}"]).unwrap();
+    /// assert_eq!(&gen.instantiate("main").unwrap(), "ein Buch");
+    /// ```
+    pub fn set_gender_classes(&mut self, classes: &[&str]) {
+        self.metadata.genders = classes.iter().map(|c| c.to_string()).collect();
+    }
+
+    /// Returns the number of genders that have a specific form in `form1/form2/...`
+    /// expressions
+    fn gender_count(&self) -> usize {
+        match self.metadata.genders.len() {
+            0 => 2,
+            n => n,
         }
     }
 
-    /// "forget" all state and instantiate a symbol
-    fn reinstantiate<R: Rng>(&self, symbol: &str, rng: &mut R) -> Result<String> {
-        let mut replaced = self.replaced.clone();
+    /// Returns true if `s` is a gender marker, i.e. one of the declared gender classes if
+    /// there are some, or `m`, `f` or `n`
+    fn is_gender_marker(&self, s: &str) -> bool {
+        if self.metadata.genders.is_empty() {
+            matches!(s, "m" | "f" | "n")
+        } else {
+            self.metadata.genders.iter().any(|c| c == s)
+        }
+    }
+
+    /// Parses a gender marker
+    fn parse_gender(&self, s: &str) -> Result<Gender> {
+        Gender::parse_class(s, &self.metadata.genders)
+    }
+
+    /// Returns the marker corresponding to a gender
+    fn gender_marker(&self, gender: Gender) -> String {
+        gender.marker(&self.metadata.genders)
+    }
+
+    /// Renders the forms of an expression for a neutral gender, when it doesn't have a
+    /// specific neutral form
+    fn neutral_forms(&self, forms: &[String]) -> Result<String> {
+        if forms.len() == 2 {
+            return self.neutral(&forms[0], &forms[1]);
+        }
+        match self.metadata.neutral_style.unwrap_or_default() {
+            NeutralStyle::Error => bail!(
+                "No neutral form in '{}', and the neutral style is set to error",
+                forms.join("/")
+            ),
+            NeutralStyle::Masculine => Ok(Self::protect(&forms[0])),
+            _ => Ok(Self::protect(&forms.join("/"))),
+        }
+    }
+
+    /// Adds a pronoun set, which can then be attached to a symbol with a `[pronouns:name]`
+    /// marker.
+    ///
+    /// Pronoun sets can also be declared in the `"pronouns"` field of a grammar's metadata.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use genere::{Gender, Generator, PronounSet};
+    /// let mut gen = Generator::new();
+    /// gen.add_pronouns("xe", PronounSet::new("xe", "xem", "xyr", "xemself", Gender::Neutral));
+    /// gen.add("hero", &["Alex[pronouns:xe]"]).unwrap();
+    /// gen.add("main", &["{hero} lost {hero:poss} sword. {Hero:subj} blamed {hero:refl}."]).unwrap();
+    /// assert_eq!(&gen.instantiate("main").unwrap(), "Alex lost xyr sword. Xe blamed xemself.");
+    /// ```
+    pub fn add_pronouns(&mut self, name: &str, pronouns: PronounSet) {
+        self.metadata.pronouns.insert(name.to_string(), pronouns);
+    }
+
+    /// Returns the pronoun set with the given name
+    fn pronoun_set(&self, name: &str) -> Result<&PronounSet> {
+        match self.metadata.pronouns.get(name) {
+            Some(pronouns) => Ok(pronouns),
+            None => bail!("Unknown pronoun set '{}'", name),
+        }
+    }
+
+    /// Returns a form of the pronouns of a symbol, as requested by `{symbol:form}`.
+    ///
+    /// If the symbol doesn't have a pronoun set, the default pronouns of the language for
+    /// its gender are used.
+    fn pronoun<R: Rng>(
+        &self,
+        symbol: &str,
+        form: &str,
+        replaced: &mut HashMap<String, Replaced>,
+        rng: &mut R,
+        stack: &mut HashSet<String>,
+    ) -> Result<String> {
+        let low_symbol = symbol.to_lowercase();
+        let gender = self.get_gender(&low_symbol, true, replaced, rng, stack)?;
+        let defaults = |gender| -> Result<PronounSet> {
+            match self.language.pronouns(gender) {
+                Some(pronouns) => Ok(pronouns),
+                None => bail!(
+                    "Symbol '{}' has no pronouns, and language '{}' has none by default",
+                    symbol,
+                    self.language.code()
+                ),
+            }
+        };
+        // For a neutral gender without specific pronouns, masculine and feminine ones are
+        // combined
+        let (masculine, feminine) = match replaced[&low_symbol].pronouns.as_deref() {
+            Some(name) => {
+                let pronouns = self.pronoun_set(name)?.clone();
+                (pronouns.clone(), pronouns)
+            }
+            None => match self.language.pronouns(gender) {
+                Some(pronouns) => (pronouns.clone(), pronouns),
+                None => (defaults(Gender::Male)?, defaults(Gender::Female)?),
+            },
+        };
+        match (masculine.form(form), feminine.form(form)) {
+            (Some(m), Some(f)) if m == f => Ok(Self::protect(m)),
+            (Some(m), Some(f)) => self.neutral(m, f),
+            _ => bail!("Unknown pronoun form '{}' in '{{{}:{}}}'", form, symbol, form),
+        }
+    }
+
+    /// Returns the gender of a symbol, or the gender of what it refers to if `referent`
+    /// is true
+    fn get_gender<R: Rng>(
+        &self,
+        symbol: &str,
+        referent: bool,
+        replaced: &mut HashMap<String, Replaced>,
+        rng: &mut R,
+        stack: &mut HashSet<String>,
+    ) -> Result<Gender> {
+        if !replaced.contains_key(symbol) {
+            self.instantiate_util(symbol, &[], replaced, rng, stack)?;
+        }
+        match replaced.get(symbol) {
+            Some(replaced) if referent => Ok(replaced.referent.unwrap_or(replaced.gender)),
+            Some(replaced) => Ok(replaced.gender),
+            None => bail!(
+                "Some symbol needs a gender to be specified by {} but it doesn't specify one",
+                symbol
+            ),
+        }
+    }
+
+    /// "forget" all state (except symbols that were explicitly set) and instantiate a symbol
+    fn reinstantiate<R: Rng>(
+        &self,
+        reference: &Reference,
+        replaced: &HashMap<String, Replaced>,
+        rng: &mut R,
+    ) -> Result<Replaced> {
+        let mut new_replaced = self.replaced.clone();
+        for (k, v) in replaced.iter().filter(|(_, v)| v.set) {
+            new_replaced.insert(k.clone(), v.clone());
+        }
         let mut stack = HashSet::new();
 
-        self.instantiate_util(symbol, &mut replaced, rng, &mut stack)
+        self.instantiate_reference(reference, &mut new_replaced, rng, &mut stack)
+    }
+
+    /// Instantiates a reference, either to a plain symbol or to a symbol with parameters
+    fn instantiate_reference<R: Rng>(
+        &self,
+        reference: &Reference,
+        replaced: &mut HashMap<String, Replaced>,
+        rng: &mut R,
+        stack: &mut HashSet<String>,
+    ) -> Result<Replaced> {
+        if reference.args.is_empty() {
+            let content =
+                self.instantiate_util(reference.symbol, &reference.tags, replaced, rng, stack)?;
+            let r = &replaced[&reference.symbol.to_lowercase()];
+            Ok(Replaced {
+                content,
+                gender: r.gender,
+                referent: r.referent,
+                pronouns: r.pronouns.clone(),
+                set: false,
+            })
+        } else {
+            self.call(reference, replaced, rng, stack)
+        }
+    }
+
+    /// Instantiates a symbol with parameters, e.g. `{greet(villain)}`.
+    ///
+    /// Each parameter is bound to the value of the corresponding argument while the
+    /// symbol is instantiated. Symbols first instantiated during the call may depend on
+    /// the parameters, so they are forgotten afterwards, as is the result: each call picks
+    /// new values.
+    fn call<R: Rng>(
+        &self,
+        reference: &Reference,
+        replaced: &mut HashMap<String, Replaced>,
+        rng: &mut R,
+        stack: &mut HashSet<String>,
+    ) -> Result<Replaced> {
+        let symbol = reference.symbol;
+        let low_symbol = symbol.to_lowercase();
+        let r = match self.replacements.get(&low_symbol) {
+            Some(r) => r,
+            None => bail!("could not find symbol {} in generator", symbol),
+        };
+        if r.params.len() != reference.args.len() {
+            bail!(
+                "Symbol '{}' takes {} argument(s), but {} were given",
+                symbol,
+                r.params.len(),
+                reference.args.len()
+            );
+        }
+
+        // Arguments are instantiated in the context of the caller
+        let mut values = Vec::with_capacity(reference.args.len());
+        for arg in &reference.args {
+            self.instantiate_util(arg, &[], replaced, rng, stack)?;
+            values.push(replaced[&arg.to_lowercase()].clone());
+        }
+
+        if stack.contains(&low_symbol) {
+            bail!(
+                "Can not instantiate, there is cyclic dependency: '{}' depends on itself!",
+                symbol
+            )
+        }
+        stack.insert(low_symbol.clone());
+
+        // Bind the parameters in a scope that is dropped after the call
+        let saved = replaced.clone();
+        for (param, value) in r.params.iter().zip(values) {
+            replaced.insert(param.clone(), value);
+        }
+        let result = self.replace_content(r, &reference.tags, replaced, rng, stack);
+        *replaced = saved;
+        stack.remove(&low_symbol);
+
+        let mut result = result?;
+        result.content = Self::capitalize(&*self.language, symbol, &result.content);
+        Ok(result)
+    }
+
+    /// Sets a symbol to a value, as requested by `{set symbol = value}`.
+    ///
+    /// The value can either be a reference to another symbol, e.g. `{name}`, some
+    /// content between quotes, e.g. `"Joan[f]"`, or a numeric expression, e.g. `2d20 + 5`.
+    fn set<R: Rng>(
+        &self,
+        symbol: &str,
+        value: &str,
+        replaced: &mut HashMap<String, Replaced>,
+        rng: &mut R,
+        stack: &mut HashSet<String>,
+    ) -> Result<()> {
+        lazy_static! {
+            static ref RE_QUOTED: Regex = Regex::new(r#"^"(.*)"$"#).unwrap();
+            static ref RE_REFERENCE: Regex = Regex::new(r"^\{(.*)\}$").unwrap();
+        }
+
+        let mut r = if let Some(caps) = RE_QUOTED.captures(value) {
+            let replacement = Replacement {
+                gender_dependency: None,
+                params: vec![],
+                content: vec![Variant::from(caps[1].to_string())],
+            };
+            self.replace_content(&replacement, &[], replaced, rng, stack)?
+        } else if let Some(caps) = RE_REFERENCE.captures(value) {
+            let reference = Self::parse_reference(&caps[1])?;
+            self.instantiate_reference(&reference, replaced, rng, stack)?
+        } else {
+            match self.evaluate(value, replaced, rng, stack) {
+                Ok(content) => Replaced {
+                    content,
+                    gender: Gender::Neutral,
+                    referent: None,
+                    pronouns: None,
+                    set: true,
+                },
+                Err(e) => bail!("Invalid value in '{{set {} = {}}}': {}", symbol, value, e),
+            }
+        };
+        r.set = true;
+        replaced.insert(symbol.to_lowercase(), r);
+        Ok(())
     }
 
     /// Capitalize the content according to the symbol.
@@ -219,18 +1303,32 @@ impl Generator {
     /// If symbol is all uppercase, content will be all uppercase.
     ///
     /// If symbol is lowercase, don't touch the content.
-    fn capitalize(symbol: &str, content: &str) -> String {
+    fn capitalize(language: &dyn Language, symbol: &str, content: &str) -> String {
+        lazy_static! {
+            static ref RE: Regex = Regex::new(r"~<\w+>").unwrap();
+            static ref RE_MARKERS: Regex = Regex::new(r"^(?:~<(?:aspirated|contract)>)*").unwrap();
+        }
+
         let left = symbol.find(char::is_uppercase);
         match left {
             Some(0) => match symbol.find(char::is_lowercase) {
+                // Aspirated h and contraction markers must be skipped
                 Some(_) => {
-                    let mut c = content.chars();
-                    match c.next() {
-                        None => unreachable!(),
-                        Some(f) => f.to_uppercase().collect::<String>() + c.as_str(),
+                    let start = RE_MARKERS.find(content).map_or(0, |m| m.end());
+                    format!("{}{}", &content[..start], language.capitalize(&content[start..]))
+                }
+                // Escaped characters must be left untouched
+                None => {
+                    let mut result = String::with_capacity(content.len());
+                    let mut last = 0;
+                    for m in RE.find_iter(content) {
+                        result.push_str(&language.to_uppercase(&content[last..m.start()]));
+                        result.push_str(m.as_str());
+                        last = m.end();
                     }
+                    result.push_str(&language.to_uppercase(&content[last..]));
+                    result
                 }
-                None => content.to_uppercase(),
             },
             _ => content.to_string(),
         }
@@ -238,200 +1336,802 @@ impl Generator {
 
     /// Replace a replacement grammer with some actual content
     /// Used to recursively instantiate each element
+    ///
+    /// If `tags` is not empty, only the variants that have all these tags can be picked.
     fn replace_content<R: Rng>(
         &self,
         r: &Replacement,
+        tags: &[&str],
         replaced: &mut HashMap<String, Replaced>,
         rng: &mut R,
         stack: &mut HashSet<String>,
     ) -> Result<Replaced> {
         lazy_static! {
-            static ref RE_REINSTANTIATE: Regex = Regex::new(r"\{\{(\w*)\}\}").unwrap();
-            static ref RE_INSTANTIATE: Regex = Regex::new(r"\{(\w*)\}").unwrap();
-            static ref RE_SET_GENDER: Regex = Regex::new(r"\[([mfn])\]").unwrap();
-            static ref RE_SLASHES: Regex =
-                Regex::new(r"([\w~<>]*)/([\w~<>]*)(?:/([\w~<>]*))?(?:\[(\w+)\])?").unwrap();
-            static ref RE_DOTS: Regex = Regex::new(
-                r"([\w~<>]+)·([\w~<>]*)(?:·([\w~<>]*))?(?:·([\w~<>]*))?(?:\[([\w~<>]+)\])?"
+            static ref RE_ACTIONS: Regex = Regex::new(concat!(
+                r"\{set\s+(\w+)\s*=\s*((?:[^{}]|\{(?:[^{}]|\{[^{}]*\})*\})*?)\s*\}|",
+                r"\{unset\s+(\w+)\s*\}"
+            ))
+            .unwrap();
+            // Content between braces is matched so it can be skipped
+            static ref RE_SET_GENDER: Regex = Regex::new(
+                r"\{(?:[^{}]|\{(?:[^{}]|\{[^{}]*\})*\})*\}|\[(\w+)\]|\[pronouns:(\w+)\]|\[ref:(\w+)\]"
             )
             .unwrap();
         }
 
         let mut gender = Gender::Neutral;
-
-        // Pick a random variant
-        let s: &str = if let Some(s) = r.content.choose(rng) {
-            s
+        let mut referent = None;
+        let mut pronouns = None;
+
+        // Pick a random variant, among those that have the required tags
+        let variants: Vec<&Variant> = r
+            .content
+            .iter()
+            .filter(|v| tags.iter().all(|t| v.tags.iter().any(|tag| tag == t)))
+            .collect();
+        let variant: Option<&Variant> = if variants.is_empty() {
+            if !tags.is_empty() {
+                bail!("No alternative has all the tags {:?}", tags);
+            }
+            None
         } else {
-            ""
+            match variants.choose_weighted(rng, |v| v.weight) {
+                Ok(v) => Some(*v),
+                Err(_) => bail!("Can not pick an alternative: all of them have a weight of 0"),
+            }
+        };
+        let s: &str = match variant {
+            Some(v) => &v.content,
+            None => "",
         };
 
-        // Set the gender of the symbol, if needed
-        // If not [m] [f] or [n] it is a dependency, not a gender set
-        {
-            let mut i = 0;
-            for caps in RE_SET_GENDER.captures_iter(s) {
-                i += 1;
-                if i > 1 {
-                    bail!(
-                        "Multiple genders in expression '{}'",
-                        s
-                    );
+        // Set the gender (or the pronouns) of the symbol, and the gender of its referent,
+        // if needed
+        // If not a gender marker (e.g. [m]), [pronouns:name] or [ref:m] it is a dependency,
+        // not a gender set
+        let mut i = 0;
+        let s = try_replace_all(&RE_SET_GENDER, s, |caps: &Captures| {
+            if let Some(m) = caps.get(3) {
+                if referent.is_some() {
+                    bail!("Multiple referent genders in expression '{}'", s);
                 }
-                match &caps[1] {
-                    "m" | "M" => gender = Gender::Male,
-                    "f" | "F" => gender = Gender::Female,
-                    "n" | "N" => gender = Gender::Neutral,
-                    _ => unreachable! {},
+                referent = Some(self.parse_gender(m.as_str())?);
+                return Ok(String::new());
+            }
+            let marker = caps.get(1).is_some_and(|m| self.is_gender_marker(m.as_str()));
+            if !marker && caps.get(2).is_none() {
+                return Ok(caps[0].to_string());
+            }
+            i += 1;
+            if i > 1 {
+                bail!(
+                    "Multiple genders in expression '{}'",
+                    s
+                );
+            }
+            match caps.get(2) {
+                Some(name) => {
+                    gender = self.pronoun_set(name.as_str())?.agreement;
+                    pronouns = Some(name.as_str().to_string());
                 }
+                None => gender = self.parse_gender(&caps[1])?,
             }
+            Ok(String::new())
+        })?;
+        if let Some(g) = variant.and_then(|v| v.gender) {
+            if i > 0 {
+                bail!(
+                    "Multiple genders in expression '{}'",
+                    s
+                );
+            }
+            gender = g;
+        }
+        if let Some(g) = variant.and_then(|v| v.referent) {
+            if referent.is_some() {
+                bail!("Multiple referent genders in expression '{}'", s);
+            }
+            referent = Some(g);
         }
 
-        let s = RE_SET_GENDER.replace_all(&s, "");
+        // Apply {set ...} and {unset ...} actions from left to right, rendering the text
+        // between them in order, so that it sees the values that are set at this point
+        let dependency = r.gender_dependency.as_deref();
+        let mut result = String::with_capacity(s.len());
+        let mut last = 0;
+        for caps in RE_ACTIONS.captures_iter(&s) {
+            let m = caps.get(0).unwrap();
+            result.push_str(&self.render(&s[last..m.start()], dependency, replaced, rng, stack)?);
+            if let Some(symbol) = caps.get(1) {
+                self.set(symbol.as_str(), &caps[2], replaced, rng, stack)?;
+            } else {
+                replaced.remove(&caps[3].to_lowercase());
+            }
+            last = m.end();
+        }
+        result.push_str(&self.render(&s[last..], dependency, replaced, rng, stack)?);
+        // The dependencies must exist (once the actions have run), even if no expression
+        // needed them
+        if let Some(dependency) = dependency {
+            self.resolve(&Self::parse_dependencies(dependency)?, replaced, rng, stack)?;
+        }
 
-        // Replace {{symbols}} with replacements, forgetting the environment and reinstiating them
-        let result = RE_REINSTANTIATE.replace_all(s.as_ref(), |caps: &Captures| {
-            self.reinstantiate(&caps[1], rng).unwrap()
-        });
+        Ok(Replaced {
+            gender,
+            content: result,
+            referent,
+            pronouns,
+            set: false,
+        })
+    }
 
-        // Replace {symbols} with replacements
-        let result = RE_INSTANTIATE.replace_all(result.as_ref(), |caps: &Captures| {
-            self.instantiate_util(&caps[1], replaced, rng, stack)
-                .unwrap()
-        });
+    /// Renders some text: replaces symbols and adapts gendered expressions, according
+    /// to the dependencies of the symbol (`dependency`) if they don't specify other ones
+    fn render<R: Rng>(
+        &self,
+        s: &str,
+        dependency: Option<&str>,
+        replaced: &mut HashMap<String, Replaced>,
+        rng: &mut R,
+        stack: &mut HashSet<String>,
+    ) -> Result<String> {
+        lazy_static! {
+            static ref RE_BRACES: Regex = Regex::new(r"\{\{([^{}]*)\}\}|\{([^{}]*)\}").unwrap();
+            static ref RE_BEFORE_REFERENCE: Regex =
+                Regex::new(r"(^|[^\w~<>/·|@*:.()\-])(\w+ +\{)").unwrap();
+            static ref RE_SLASHES: Regex = Regex::new(
+                r"([\w~<>]*)((?:/[\w~<>]*)+)(?:\((\w+)\))?(?:\[([\w,#:]+)\])?"
+            )
+            .unwrap();
+            static ref RE_DOTS: Regex = Regex::new(concat!(
+                r"([\w~<>]+)·([\w~<>]*)(?:·([\w~<>]*))?(?:·([\w~<>]*))?",
+                r"(?:\((\w+)\))?(?:\[([\w~<>,#:]+)\])?"
+            ))
+            .unwrap();
+            static ref RE_PLURAL: Regex =
+                Regex::new(r"([\w~<>]+)\((\w+)\)(?:\[([\w,#:]+)\])?").unwrap();
+            static ref RE_WORDS: Regex =
+                Regex::new(r"([\w@~<>]+(?:[/*:]\w+)?)(?:\[([\w,#:]+)\])?").unwrap();
+            static ref RE_EXPLICIT: Regex = Regex::new(concat!(
+                r"<((?:[^<>|~]|~<\w+>)*(?:\|(?:[^<>|~]|~<\w+>)*)+)>",
+                r"(?:\((\w+)\))?(?:\[([\w,#:]+)\])?"
+            ))
+            .unwrap();
+            static ref RE_PERIOD: Regex =
+                Regex::new(r"([\w~<>]+)\.(\p{Ll}+)(?:\.(s))?(?:\[([\w,#:]+)\])?").unwrap();
+            static ref RE_PARENTHESES: Regex =
+                Regex::new(r"([\w~<>]+)\((\p{Ll}+)\)(s)?(?:\[([\w,#:]+)\])?").unwrap();
+            static ref RE_HYPHEN: Regex =
+                Regex::new(r"([\w~<>]+)-(\p{Ll}+)(?:-(s))?(?:\[([\w,#:]+)\])?").unwrap();
+        }
 
-        // Gender adaptation, if needed
-        // Find the gender to replace
-        let dependency = r.gender_dependency.as_ref();
-        let gender_adapt = if let Some(key) = dependency {
-            self.get_gender(key, replaced, rng, stack)?
+        // Words right before a reference, e.g. "le" in "le {arme}", can be contracted
+        let s = if self.metadata.contractions == Some(true) {
+            RE_BEFORE_REFERENCE.replace_all(s, "${1}~<contract>${2}")
         } else {
-            Gender::Neutral
+            Cow::Borrowed(s)
         };
 
-        // Replacement of the form "content·e" (used in french)
-        let result = RE_DOTS.replace_all(&result, |caps: &Captures| {
-            let mut len = 3;
-            if caps.get(3).is_some() {
-                len += 1;
-            }
-            if caps.get(4).is_some() {
-                len += 1;
+        // Replace {{symbols}} with replacements, forgetting the environment and
+        // reinstiating them, and {symbols} with replacements
+        let result = try_replace_all(&RE_BRACES, &s, |caps: &Captures| match caps.get(1) {
+            Some(m) => self.expand(m.as_str(), true, dependency, replaced, rng, stack),
+            None => self.expand(&caps[2], false, dependency, replaced, rng, stack),
+        })?;
+
+        // Gender adaptation, if needed
+        // Find the gender and count to use by default. They are only resolved when an
+        // expression needs them, so that a `{set ...}` action can define the dependency
+        let default = match dependency {
+            Some(dependency) => Self::parse_dependencies(dependency)?,
+            None => Dependencies::default(),
+        };
+        let resolved = Cell::new(None);
+        let resolve_default = |replaced: &mut HashMap<String, Replaced>,
+                               rng: &mut R,
+                               stack: &mut HashSet<String>|
+         -> Result<(Gender, Option<i64>)> {
+            if let Some(resolved) = resolved.get() {
+                return Ok(resolved);
             }
-            let gender = if caps.get(5).is_some() {
-                self.get_gender(&caps[5], replaced, rng, stack).unwrap()
-            } else {
-                gender_adapt
+            let (gender, count) = self.resolve(&default, replaced, rng, stack)?;
+            let result = (gender.unwrap_or(Gender::Neutral), count);
+            resolved.set(Some(result));
+            Ok(result)
+        };
+        let language = &*self.language;
+
+        // Returns the gender and count for an expression, given its own dependencies, and
+        // whether it depends on a count only
+        let adapt = |spec: Option<&str>,
+                     replaced: &mut HashMap<String, Replaced>,
+                     rng: &mut R,
+                     stack: &mut HashSet<String>|
+         -> Result<(Gender, Option<i64>, bool)> {
+            let spec = match spec {
+                Some(spec) => Self::parse_dependencies(spec)?,
+                None => {
+                    let count_only = default.gender.is_empty() && default.count.is_some();
+                    let (gender, count) = resolve_default(replaced, rng, stack)?;
+                    return Ok((gender, count, count_only));
+                }
             };
-            match gender {
-                Gender::Male => match len {
-                    3 => format!("{}", &caps[1]),
-                    4 => format!("{}{}", &caps[1], &caps[2]),
-                    5 => format!("{}{}{}", &caps[1], &caps[2], &caps[4]),
-                    _ => unreachable! {},
-                },
-                Gender::Female => match len {
-                    3 => format!("{}{}", &caps[1], &caps[2]),
-                    4 => format!("{}{}", &caps[1], &caps[3]),
-                    5 => format!("{}{}{}", &caps[1], &caps[3], &caps[4]),
-                    _ => unreachable! {},
-                },
-                Gender::Neutral => match len {
-                    3 => format!("{rad}/{rad}{f}", rad = &caps[1], f = &caps[2]),
-                    4 => format!(
-                        "{rad}{m}/{rad}{f}",
-                        rad = &caps[1],
-                        m = &caps[2],
-                        f = &caps[3]
+            let (gender, count) = self.resolve(&spec, replaced, rng, stack)?;
+            let gender = match gender {
+                Some(gender) => gender,
+                None => resolve_default(replaced, rng, stack)?.0,
+            };
+            let count = match count {
+                None if default.count.is_some() => resolve_default(replaced, rng, stack)?.1,
+                count => count,
+            };
+            Ok((gender, count, spec.gender.is_empty() && spec.count.is_some()))
+        };
+
+        // Adds a plural suffix, e.g. the `s` of `(s)`, if the count requires it, or
+        // leaves it between parentheses if there is no count
+        let pluralize = |form: String, suffix: Option<&str>, count: Option<i64>| match (suffix, count) {
+            (None, _) => form,
+            (Some(suffix), None) => format!("{}({})", form, suffix),
+            (Some(suffix), Some(n)) => {
+                if language.plural_category(n) == PluralCategory::One {
+                    form
+                } else {
+                    form + suffix
+                }
+            }
+        };
+
+        // Picks one of the forms of an alternation: Male/Female[/Neutral] (or one form per
+        // gender class, and optionally a neutral one), or Singular/Plural if it only depends
+        // on a count
+        let genders = self.gender_count();
+        let alternate = |expr: &str,
+                         forms: &[&str],
+                         suffix: Option<&str>,
+                         spec: Option<&str>,
+                         replaced: &mut HashMap<String, Replaced>,
+                         rng: &mut R,
+                         stack: &mut HashSet<String>|
+         -> Result<String> {
+            let (gender, count, count_only) = adapt(spec, replaced, rng, stack)?;
+            if let (true, Some(n)) = (count_only, count) {
+                return Ok(plural::select(n, language, forms).to_string());
+            }
+
+            if forms.len() > genders + 1 {
+                bail!(
+                    "Expression '{}' has {} forms, but there are only {} genders",
+                    expr,
+                    forms.len(),
+                    genders
+                );
+            }
+            let forms: Vec<String> = forms
+                .iter()
+                .map(|form| pluralize(form.to_string(), suffix, count))
+                .collect();
+            match gender.slot() {
+                Some(i) if i < forms.len() => Ok(forms[i].clone()),
+                Some(_) => bail!(
+                    "Expression '{}' has no form for gender '{}'",
+                    expr,
+                    self.gender_marker(gender)
+                ),
+                None if forms.len() > genders => Ok(forms[genders].clone()),
+                None => self.neutral_forms(&forms),
+            }
+        };
+
+        // Explicit alternations, e.g. "<il|elle|iel>", which aren't adapted again
+        let result = if self.has_marker(Marker::Explicit) {
+            try_replace_all(&RE_EXPLICIT, &result, |caps: &Captures| {
+                let forms: Vec<&str> = caps[1].split('|').collect();
+                let suffix = caps.get(2).map(|m| m.as_str());
+                let spec = caps.get(3).map(|m| m.as_str());
+                let form = alternate(&caps[0], &forms, suffix, spec, replaced, rng, stack)?;
+                Ok(self.contractible(Self::protect(&form)))
+            })?
+        } else {
+            result
+        };
+
+        // Words with an inclusive marker that the language understands, e.g. "amig@s"
+        let result = if self.has_marker(Marker::Inclusive) {
+            try_replace_all(&RE_WORDS, &result, |caps: &Captures| {
+                // Neutral forms, e.g. "amigues", are only understood with a dependency
+                let spec = caps.get(2).map(|m| m.as_str());
+                let forms = match spec {
+                    Some(_) => language
+                        .split_inclusive(&caps[1])
+                        .or_else(|| language.split_neutral(&caps[1])),
+                    None => language.split_inclusive(&caps[1]),
+                };
+                let (male, female) = match forms {
+                    Some(forms) => forms,
+                    None => return Ok(caps[0].to_string()),
+                };
+                let (gender, _, _) = adapt(spec, replaced, rng, stack)?;
+                let form = match gender.slot() {
+                    Some(0) => male,
+                    Some(1) => female,
+                    Some(_) => bail!(
+                        "Expression '{}' has no form for gender '{}'",
+                        &caps[0],
+                        self.gender_marker(gender)
+                    ),
+                    None => self.neutral(&male, &female)?,
+                };
+                Ok(self.contractible(form))
+            })?
+        } else {
+            result
+        };
+
+        // Syntaxes that must be enabled, e.g. "étudiant.e.s"
+        let mut result = result;
+        let markers: [(Marker, &Regex); 3] = [
+            (Marker::Period, &RE_PERIOD),
+            (Marker::Parentheses, &RE_PARENTHESES),
+            (Marker::Hyphen, &RE_HYPHEN),
+        ];
+        for (marker, re) in markers.iter() {
+            if !self.has_marker(*marker) {
+                continue;
+            }
+            result = try_replace_all(re, &result, |caps: &Captures| {
+                let plural = caps.get(3).map_or("", |m| m.as_str());
+                let forms = language::split_french_suffix(&caps[1], &caps[2], plural);
+                let (male, female) = match forms {
+                    Some(forms) => forms,
+                    None => return Ok(caps[0].to_string()),
+                };
+                let (gender, _, _) = adapt(caps.get(4).map(|m| m.as_str()), replaced, rng, stack)?;
+                let form = match gender.slot() {
+                    Some(0) => male,
+                    Some(1) => female,
+                    Some(_) => bail!(
+                        "Expression '{}' has no form for gender '{}'",
+                        &caps[0],
+                        self.gender_marker(gender)
+                    ),
+                    None => self.neutral(&male, &female)?,
+                };
+                Ok(self.contractible(form))
+            })?;
+        }
+
+        // Replacement of the form "content·e" (used in french)
+        let result = if self.has_marker(Marker::MedianPoint) {
+            try_replace_all(&RE_DOTS, &result, |caps: &Captures| {
+                let spec = caps.get(6).map(|m| m.as_str());
+                let (gender, count, _) = adapt(spec, replaced, rng, stack)?;
+                let (male, female) = match (caps.get(3), caps.get(4)) {
+                    (None, _) => (caps[1].to_string(), format!("{}{}", &caps[1], &caps[2])),
+                    (Some(_), None) => (
+                        format!("{}{}", &caps[1], &caps[2]),
+                        format!("{}{}", &caps[1], &caps[3]),
                     ),
-                    5 => format!(
-                        "{rad}{m}{s}/{rad}{f}{s}",
-                        rad = &caps[1],
-                        m = &caps[2],
-                        f = &caps[3],
-                        s = &caps[4]
+                    (Some(_), Some(_)) => (
+                        format!("{}{}{}", &caps[1], &caps[2], &caps[4]),
+                        format!("{}{}{}", &caps[1], &caps[3], &caps[4]),
                     ),
-                    _ => unreachable! {},
-                },
+                };
+                let suffix = caps.get(5).map(|m| m.as_str());
+                let male = pluralize(male, suffix, count);
+                let female = pluralize(female, suffix, count);
+                let form = match gender.slot() {
+                    Some(0) => male,
+                    Some(1) => female,
+                    Some(_) => bail!(
+                        "Expression '{}' has no form for gender '{}'",
+                        &caps[0],
+                        self.gender_marker(gender)
+                    ),
+                    None => self.neutral(&male, &female)?,
+                };
+                Ok(self.contractible(form))
+            })?
+        } else {
+            result
+        };
+
+        // Replacement of the form Male/Female[/Neutral], or Singular/Plural
+        let result = if self.has_marker(Marker::Slash) {
+            try_replace_all(&RE_SLASHES, &result, |caps: &Captures| {
+                let mut forms = vec![&caps[1]];
+                forms.extend(caps[2].split('/').skip(1));
+                let suffix = caps.get(3).map(|m| m.as_str());
+                let spec = caps.get(4).map(|m| m.as_str());
+                // Without a dependency, slashes that don't fit the genders are ordinary text,
+                // e.g. "/usr/local/share"
+                if spec.is_none() && forms.len() > genders + 1 {
+                    return Ok(caps[0].to_string());
+                }
+                let form = alternate(&caps[0], &forms, suffix, spec, replaced, rng, stack)?;
+                Ok(self.contractible(form))
+            })?
+        } else {
+            result
+        };
+
+        // Plural of the form "word(s)", only if there is a count and a plural suffix
+        let result = try_replace_all(&RE_PLURAL, &result, |caps: &Captures| {
+            if !language.is_plural_suffix(&caps[1], &caps[2]) {
+                return Ok(caps[0].to_string());
             }
-        });
+            let (_, count, _) = adapt(caps.get(3).map(|m| m.as_str()), replaced, rng, stack)?;
+            Ok(match count {
+                Some(_) => pluralize(caps[1].to_string(), Some(&caps[2]), count),
+                None => caps[0].to_string(),
+            })
+        })?;
+
+        Ok(result)
+    }
 
-        // Replacement of the form Male/Female[/Neutral]
-        let result = RE_SLASHES.replace_all(&result, |caps: &Captures| {
-            let gender = if caps.get(4).is_some() {
-                self.get_gender(&caps[4], replaced, rng, stack).unwrap()
-            } else {
-                gender_adapt
+    /// Parses dependencies, of the form `symbol`, `#count`, `symbol,#count`, or
+    /// `symbol1,symbol2` for a group. Symbols can be followed by `:ref` to use the gender
+    /// of their referent.
+    fn parse_dependencies(s: &str) -> Result<Dependencies<'_>> {
+        let mut dependencies = Dependencies::default();
+        for item in s.split(',').map(|item| item.trim()) {
+            let (item, referent) = match item.strip_suffix(":ref") {
+                Some(item) => (item, true),
+                None => (item, false),
             };
-
-            match gender {
-                Gender::Male => format!("{}", &caps[1]),
-                Gender::Female => format!("{}", &caps[2]),
-                Gender::Neutral => {
-                    if caps.get(3).is_some() {
-                        format!("{}", &caps[3])
-                    } else {
-                        format!("{}/{}", &caps[1], &caps[2])
-                    }
+            match item.strip_prefix('#') {
+                Some(name) if !name.is_empty() && !referent && dependencies.count.is_none() => {
+                    dependencies.count = Some(name)
                 }
+                None if !item.is_empty() && !item.contains(':') => {
+                    dependencies.gender.push((item, referent))
+                }
+                _ => bail!("Invalid dependencies '[{}]'", s),
             }
-        });
+        }
+        Ok(dependencies)
+    }
 
-        Ok(Replaced {
-            gender: gender,
-            content: result.to_string()
+    /// Returns the gender and the count corresponding to some dependencies, if they specify
+    /// them.
+    ///
+    /// For a group, the count is the number of its members, unless it is given explicitly.
+    fn resolve<R: Rng>(
+        &self,
+        dependencies: &Dependencies,
+        replaced: &mut HashMap<String, Replaced>,
+        rng: &mut R,
+        stack: &mut HashSet<String>,
+    ) -> Result<(Option<Gender>, Option<i64>)> {
+        let mut genders = Vec::with_capacity(dependencies.gender.len());
+        for (key, referent) in &dependencies.gender {
+            genders.push(self.get_gender(key, *referent, replaced, rng, stack)?);
+        }
+        let count = match dependencies.count {
+            Some(key) => Some(self.get_number(key, replaced, rng, stack)?),
+            None if genders.len() > 1 => Some(genders.len() as i64),
+            None => None,
+        };
+        Ok((self.group_gender(&genders), count))
+    }
+
+    /// Returns the gender of a group, according to the group rule
+    fn group_gender(&self, genders: &[Gender]) -> Option<Gender> {
+        let first = *genders.first()?;
+        let same = genders.iter().all(|g| *g == first);
+        Some(match self.metadata.group_rule.unwrap_or_default() {
+            _ if same => first,
+            _ if genders.iter().all(|g| *g == Gender::Neutral) => Gender::Neutral,
+            GroupRule::Masculine | GroupRule::FeminineIfAll => Gender::Male,
+            GroupRule::NeutralIfMixed => Gender::Neutral,
         })
     }
 
-    /// Used to recursively instantiate each element
-    fn instantiate_util<R: Rng>(
+    /// Expands the content of braces: a reference to a symbol or a numeric expression,
+    /// optionally followed by modifiers, e.g. `{n.words}` or `{book.title}`.
+    ///
+    /// If `reinstantiate` is true, references forget the current environment. `dependency`
+    /// is the default dependency, used by modifiers that need a gender.
+    fn expand<R: Rng>(
         &self,
-        symbol: &str,
+        s: &str,
+        reinstantiate: bool,
+        dependency: Option<&str>,
         replaced: &mut HashMap<String, Replaced>,
         rng: &mut R,
         stack: &mut HashSet<String>,
     ) -> Result<String> {
-        let low_symbol = symbol.to_lowercase();
-
-        // If symbol has already been instantiated, early return
-        if let Some(r) = replaced.get(&low_symbol) {
-            return Ok(Self::capitalize(symbol, &r.content));
+        lazy_static! {
+            static ref RE_MODIFIERS: Regex =
+                Regex::new(r"^([^.]*)((?:\.\w+)+)(?:\[([\w,#:]+)\])?$").unwrap();
+            static ref RE_PRONOUN: Regex = Regex::new(r"^\s*(\w+)\s*:\s*(\w+)\s*$").unwrap();
+            static ref RE_ARTICLE: Regex =
+                Regex::new(r"^\s*(\w+)((?:\.\w+)*)\s*:\s*(.*?)\s*$").unwrap();
+            static ref RE_DIE: Regex = Regex::new(r"^\s*d\d+\s*$").unwrap();
         }
 
-        if stack.contains(&low_symbol) {
-            bail!(
-                "Can not instantiate, there is cyclic dependency: '{}' depends on itself!",
-                symbol
-            )
+        // Articles, e.g. `{a:weapon}` or `{der.akk:weapon}`, unless it is a pronoun of a
+        // symbol with the same name
+        if let Some(caps) = RE_ARTICLE.captures(s) {
+            if self.language.articles().contains(&caps[1].to_lowercase().as_str())
+                && !(caps[2].is_empty() && PronounSet::FORMS.contains(&&caps[3]))
+            {
+                let reference = Self::parse_reference(caps.get(3).unwrap().as_str())?;
+                let article = format!("{}{}", &caps[1], &caps[2]);
+                return self.article(&article, &reference, reinstantiate, replaced, rng, stack);
+            }
         }
-        stack.insert(low_symbol.clone());
-
-        if let Some(r) = self.replacements.get(&low_symbol) {
-            let r = self.replace_content(r, replaced, rng, stack)?;
 
-            replaced.insert(
-                low_symbol.clone(),
-                r);
-        } else {
-            bail!("could not find symbol {} in generator", symbol);
+        // Pronouns, e.g. `{hero:subj}`
+        if let Some(caps) = RE_PRONOUN.captures(s) {
+            let symbol = caps.get(1).unwrap().as_str();
+            let result = self.pronoun(symbol, &caps[2], replaced, rng, stack)?;
+            return Ok(Self::capitalize(&*self.language, symbol, &result));
         }
 
-        stack.remove(&low_symbol);
+        let (base, modifiers, own_dependency) = match RE_MODIFIERS.captures(s) {
+            Some(caps) => (
+                caps.get(1).unwrap().as_str(),
+                caps.get(2).unwrap().as_str(),
+                caps.get(3).map(|m| m.as_str()),
+            ),
+            None => (s, "", None),
+        };
 
-        match replaced.get(&low_symbol) {
-            Some(replaced) => Ok(Self::capitalize(symbol, &replaced.content)),
-            None => unreachable! {},
+        // Anything that isn't a reference is a numeric expression, as is a single die,
+        // e.g. `{d20}`, unless there is a symbol with that name
+        let (mut result, symbol) = match Self::parse_reference(base) {
+            Ok(reference)
+                if RE_DIE.is_match(base)
+                    && !self.replacements.contains_key(&reference.symbol.to_lowercase())
+                    && !replaced.contains_key(&reference.symbol.to_lowercase()) =>
+            {
+                (self.evaluate(base, replaced, rng, stack)?, None)
+            }
+            Ok(reference) if reinstantiate => {
+                (self.reinstantiate(&reference, replaced, rng)?.content, Some(reference.symbol))
+            }
+            Ok(reference) => (
+                self.instantiate_reference(&reference, replaced, rng, stack)?.content,
+                Some(reference.symbol),
+            ),
+            Err(_) => (self.evaluate(base, replaced, rng, stack)?, None),
+        };
+
+        for modifier in modifiers.split('.').filter(|m| !m.is_empty()) {
+            result = match modifier {
+                "words" => {
+                    let n = match result.trim().parse::<i64>() {
+                        Ok(n) => n,
+                        Err(_) => bail!(
+                            "Modifier '.words' needs a number, but '{{{}}}' is '{}'",
+                            base,
+                            result
+                        ),
+                    };
+                    let dependencies = match own_dependency.or(dependency) {
+                        Some(dependency) => Self::parse_dependencies(dependency)?,
+                        None => Dependencies::default(),
+                    };
+                    let gender = self
+                        .resolve(&dependencies, replaced, rng, stack)?
+                        .0
+                        .unwrap_or(Gender::Neutral);
+                    let words = |gender| -> Result<String> {
+                        match self.language.number_to_words(n, gender) {
+                            Some(words) => Ok(words),
+                            None => bail!(
+                                "Spelling out numbers is not supported for language '{}'",
+                                self.language.code()
+                            ),
+                        }
+                    };
+                    match gender {
+                        Gender::Neutral => {
+                            self.neutral_phrase(&words(Gender::Male)?, &words(Gender::Female)?)?
+                        }
+                        _ => Self::protect(&words(gender)?),
+                    }
+                }
+                "title" => self.language.title_case(&result),
+                _ => bail!("Unknown modifier '.{}' in '{{{}}}'", modifier, s),
+            };
+        }
+        if let Some(symbol) = symbol {
+            result = Self::capitalize(&*self.language, symbol, &result);
         }
+        Ok(result)
     }
 
-    /// Instantiate a replacement symbol
-    pub fn instantiate(&self, symbol: &str) -> Result<String> {
+    /// Instantiates a reference preceded by the article that it requires, as requested by
+    /// `{a:symbol}` or `{der.akk:symbol}`.
+    ///
+    /// `article` is the name of the article followed by its modifiers, e.g. "der.akk", and
+    /// the article is capitalized if it is.
+    fn article<R: Rng>(
+        &self,
+        article: &str,
+        reference: &Reference,
+        reinstantiate: bool,
+        replaced: &mut HashMap<String, Replaced>,
+        rng: &mut R,
+        stack: &mut HashSet<String>,
+    ) -> Result<String> {
+        lazy_static! {
+            static ref RE_ESCAPED: Regex = Regex::new(r"^(?:~<\w+>)*").unwrap();
+        }
+
+        let r = if reinstantiate {
+            self.reinstantiate(reference, replaced, rng)?
+        } else {
+            self.instantiate_reference(reference, replaced, rng, stack)?
+        };
+        // Escaped characters, such as the aspirated h marker, are skipped
+        let word = RE_ESCAPED.replace(&r.content, "");
+        let mut modifiers = article.split('.');
+        let article = modifiers.next().unwrap_or_default();
+        let name = article.to_lowercase();
+        let modifiers: Vec<_> = modifiers.collect();
+        let get = |gender| -> Result<String> {
+            match self.language.article(&name, &modifiers, &word, gender) {
+                Some(article) => Ok(article),
+                None if modifiers.is_empty() => bail!(
+                    "Language '{}' doesn't have article '{}'",
+                    self.language.code(),
+                    article
+                ),
+                None => bail!(
+                    "Language '{}' doesn't have article '{}' with modifiers '{}'",
+                    self.language.code(),
+                    article,
+                    modifiers.join(".")
+                ),
+            }
+        };
+        let result = match r.gender {
+            Gender::Neutral => {
+                let (masculine, feminine) = (get(Gender::Male)?, get(Gender::Female)?);
+                if masculine == feminine {
+                    Self::protect(&masculine)
+                } else {
+                    self.neutral(&masculine, &feminine)?
+                }
+            }
+            gender => Self::protect(&get(gender)?),
+        };
+        let result = if article.starts_with(char::is_uppercase) {
+            self.language.capitalize(&result)
+        } else {
+            result
+        };
+        Ok(format!("{} {}", self.contractible(result), r.content))
+    }
+
+    /// Evaluates a numeric expression, e.g. `2d6 + gold`.
+    ///
+    /// Symbols used in the expression are instantiated, and their content must be a number.
+    fn evaluate<R: Rng>(
+        &self,
+        s: &str,
+        replaced: &mut HashMap<String, Replaced>,
+        rng: &mut R,
+        stack: &mut HashSet<String>,
+    ) -> Result<String> {
+        let expr = Expr::parse(s)?;
+        let mut values = HashMap::new();
+        for symbol in expr.symbols() {
+            values.insert(symbol, self.get_number(symbol, replaced, rng, stack)?);
+        }
+        Ok(expr.eval(rng, &values)?.to_string())
+    }
+
+    /// Returns the value of a symbol, which must be a number
+    fn get_number<R: Rng>(
+        &self,
+        symbol: &str,
+        replaced: &mut HashMap<String, Replaced>,
+        rng: &mut R,
+        stack: &mut HashSet<String>,
+    ) -> Result<i64> {
+        let content = self.instantiate_util(symbol, &[], replaced, rng, stack)?;
+        match content.trim().parse::<i64>() {
+            Ok(n) => Ok(n),
+            Err(_) => bail!(
+                "Symbol '{}' is used as a number, but its value, '{}', is not a number",
+                symbol,
+                content
+            ),
+        }
+    }
+
+    /// Splits a symbol reference of the form `symbol(arg1, arg2)#tag1#tag2`
+    ///
+    /// Symbol names that start with a digit are not considered as references, since they
+    /// are numeric expressions.
+    fn parse_reference(s: &str) -> Result<Reference<'_>> {
+        lazy_static! {
+            static ref RE: Regex =
+                Regex::new(r"^((?:[^\W\d]\w*)?)(?:\(\s*(\w+(?:\s*,\s*\w+)*)?\s*\))?((?:#\w+)*)$").unwrap();
+        }
+
+        match RE.captures(s) {
+            Some(caps) => {
+                let symbol = caps.get(1).unwrap().as_str();
+                let args = match caps.get(2) {
+                    Some(m) => m.as_str().split(',').map(|a| a.trim()).collect(),
+                    None => vec![],
+                };
+                let tags = caps
+                    .get(3)
+                    .unwrap()
+                    .as_str()
+                    .split('#')
+                    .filter(|t| !t.is_empty())
+                    .collect();
+                Ok(Reference { symbol, args, tags })
+            }
+            None => bail!("Invalid expression '{{{}}}'", s),
+        }
+    }
+
+    /// Used to recursively instantiate each element
+    fn instantiate_util<R: Rng>(
+        &self,
+        symbol: &str,
+        tags: &[&str],
+        replaced: &mut HashMap<String, Replaced>,
+        rng: &mut R,
+        stack: &mut HashSet<String>,
+    ) -> Result<String> {
+        let low_symbol = symbol.to_lowercase();
+
+        // If symbol has already been instantiated, early return
+        if let Some(r) = replaced.get(&low_symbol) {
+            return Ok(Self::capitalize(&*self.language, symbol, &r.content));
+        }
+
+        if stack.contains(&low_symbol) {
+            bail!(
+                "Can not instantiate, there is cyclic dependency: '{}' depends on itself!",
+                symbol
+            )
+        }
+        stack.insert(low_symbol.clone());
+
+        if let Some(r) = self.replacements.get(&low_symbol) {
+            if !r.params.is_empty() {
+                bail!(
+                    "Symbol '{}' takes {} argument(s), but none were given",
+                    symbol,
+                    r.params.len()
+                );
+            }
+            let r = self.replace_content(r, tags, replaced, rng, stack)?;
+
+            replaced.insert(
+                low_symbol.clone(),
+                r);
+        } else {
+            bail!("could not find symbol {} in generator", symbol);
+        }
+
+        stack.remove(&low_symbol);
+
+        match replaced.get(&low_symbol) {
+            Some(replaced) => Ok(Self::capitalize(&*self.language, symbol, &replaced.content)),
+            None => unreachable! {},
+        }
+    }
+
+    /// Instantiate a replacement symbol
+    pub fn instantiate(&self, symbol: &str) -> Result<String> {
         let mut replaced = self.replaced.clone();
         let mut rng = thread_rng();
         let mut set = HashSet::new();
 
-        let final_s = self.instantiate_util(symbol, &mut replaced, &mut rng, &mut set)?;
-        Ok(Self::post_process(final_s))
+        let final_s = self.instantiate_util(symbol, &[], &mut replaced, &mut rng, &mut set)?;
+        Ok(self.finish(&final_s))
     }
 
     /// Instantiate a single message without adding it as a symbol
@@ -445,7 +2145,7 @@ impl Generator {
     /// # Arguments
     ///
     /// * s: a string (or `&str`) containing the text you want to display, which can used
-    /// the `{symbol}` syntax to expand other symbols to their replacements.
+    ///   the `{symbol}` syntax to expand other symbols to their replacements.
     /// * v: a list of pairs containing symbols and replacements values (can be empty).
     ///
     /// # Example
@@ -468,19 +2168,21 @@ impl Generator {
             let symbol = symbol.to_lowercase();
             let replacement = Replacement {
                 gender_dependency: None,
-                content: vec![r.to_string()],
+                params: vec![],
+                content: vec![Variant::from(Self::pre_process(r.to_string()))],
             };
-            let r = self.replace_content(&replacement, &mut replaced, &mut rng, &mut set)?;
+            let r = self.replace_content(&replacement, &[], &mut replaced, &mut rng, &mut set)?;
             replaced.insert(symbol, r);
         }
 
         let replacement = Replacement{
             gender_dependency: None,
-            content: vec![s.into()],
+            params: vec![],
+            content: vec![Variant::from(Self::pre_process(s.into()))],
         };
 
-        let r = self.replace_content(&replacement, &mut replaced, &mut rng, &mut set)?;
-        Ok(r.content)
+        let r = self.replace_content(&replacement, &[], &mut replaced, &mut rng, &mut set)?;
+        Ok(self.finish(&r.content))
     }
 
         
@@ -492,239 +2194,1055 @@ impl Generator {
         let mut rng = SmallRng::seed_from_u64(seed);
         let mut set = HashSet::new();
 
-        let final_s = self.instantiate_util(symbol, &mut replaced, &mut rng, &mut set)?;
-        Ok(Self::post_process(final_s))
+        let final_s = self.instantiate_util(symbol, &[], &mut replaced, &mut rng, &mut set)?;
+        Ok(self.finish(&final_s))
     }
 }
 
-///////////////////////////////////////////////////////////////////////////////////////////
-//                                    TESTS
-///////////////////////////////////////////////////////////////////////////////////////////
-
+///////////////////////////////////////////////////////////////////////////////////////////
+//                                    TESTS
+///////////////////////////////////////////////////////////////////////////////////////////
+
+#[test]
+fn add_1() {
+    let mut gen = Generator::new();
+    gen.add("Test", &["foo", "bar"]).unwrap();
+}
+
+#[test]
+fn add_json() {
+    let mut gen = Generator::new();
+    gen.add_json(
+        r#"
+{
+   "Test": ["foo", "bar"]
+}"#,
+    )
+    .unwrap();
+}
+
+#[test]
+fn missing_symbol() {
+    let gen = Generator::new();
+    assert!(gen.instantiate("foo").is_err());
+}
+
+#[test]
+fn replacement_1() {
+    let mut gen = Generator::new();
+    gen.add("foo", &["hello"]).unwrap();
+    gen.add("bar", &["{foo} world"]).unwrap();
+    assert_eq!(gen.instantiate("bar").unwrap(), String::from("hello world"));
+}
+
+#[test]
+fn replacement_2() {
+    let mut gen = Generator::new();
+    gen.add("foo", &["hello"]).unwrap();
+    gen.add("bar", &["world"]).unwrap();
+    gen.add("baz", &["{foo} {bar}"]).unwrap();
+    assert_eq!(gen.instantiate("baz").unwrap(), String::from("hello world"));
+}
+
+#[test]
+fn gender_1() {
+    let mut gen = Generator::new();
+    gen.add("foo[plop]", &["He/She is happy"]).unwrap();
+    gen.set_gender("plop", Gender::Male);
+    assert_eq!(&gen.instantiate("foo").unwrap(), "He is happy");
+    gen.set_gender("plop", Gender::Female);
+    assert_eq!(&gen.instantiate("foo").unwrap(), "She is happy");
+}
+
+#[test]
+fn gender_2() {
+    let mut gen = Generator::new();
+    gen.add("plop", &["Joe[m]"]).unwrap();
+    gen.add("foo[plop]", &["He/She is happy"]).unwrap();
+    assert_eq!(&gen.instantiate("foo").unwrap(), "He is happy");
+}
+
+#[test]
+fn gender_3() {
+    let mut gen = Generator::new();
+    gen.add("plop", &["Joe[m]"]).unwrap();
+    gen.add("foo", &["He/She[plop] is happy"]).unwrap();
+    assert_eq!(&gen.instantiate("foo").unwrap(), "He is happy");
+}
+
+#[test]
+fn gender_4() {
+    let mut gen = Generator::new();
+    gen.add("arme", &["batte[f]"]).unwrap();
+    gen.add("foo", &["Un homme au/à~ la[arme] {arme} facile"])
+        .unwrap();
+    assert_eq!(
+        &gen.instantiate("foo").unwrap(),
+        "Un homme à la batte facile"
+    );
+}
+
+#[test]
+fn cyclic() {
+    let mut gen = Generator::new();
+    let json = r#"
+{
+   "a[b]": ["Foo"],
+   "b[a]": ["Bar"]
+}"#;
+    gen.add_json(json).unwrap();
+    assert!(gen.instantiate("a").is_err());
+}
+
+#[test]
+fn unexisting() {
+    let mut gen = Generator::new();
+    let json = r#"
+{
+   "a[b]": ["Foo"],
+   "b[c]": ["Bar"]
+}"#;
+    gen.add_json(json).unwrap();
+    assert!(gen.instantiate("a").is_err());
+}
+
+#[test]
+fn pre_process() {
+    let s = Generator::pre_process(r"foobarbaz".to_string());
+    assert_eq!(&s, "foobarbaz");
+
+    let s = Generator::pre_process(r"~foobarbaz".to_string());
+    assert_eq!(&s, "foobarbaz");
+
+    let s = Generator::pre_process(r"~~foobarbaz".to_string());
+    assert_eq!(&s, r"~<tilde>foobarbaz");
+
+    let s = Generator::pre_process(r"foo~ bar~ baz".to_string());
+    assert_eq!(&s, r"foo~<space>bar~<space>baz");
+
+    let s = Generator::pre_process(r"~[foobarbaz~]".to_string());
+    assert_eq!(&s, r"~<leftsquare>foobarbaz~<rightsquare>");
+
+    let s = Generator::pre_process(r"~{foobarbaz~}".to_string());
+    assert_eq!(&s, r"~<leftcurly>foobarbaz~<rightcurly>");
+
+    let s = Generator::pre_process(r"foo/bar/baz".to_string());
+    assert_eq!(&s, r"foo/bar/baz");
+
+    let s = Generator::pre_process(r"foo~/bar~/baz".to_string());
+    assert_eq!(&s, r"foo~<slash>bar~<slash>baz");
+
+    let s = Generator::pre_process(r"foo~·bar~·baz".to_string());
+    assert_eq!(&s, r"foo~<median>bar~<median>baz");
+
+    let s = Generator::pre_process(r"amig~@s Lehrer~*in Lehrer~:in".to_string());
+    assert_eq!(&s, r"amig~<at>s Lehrer~<star>in Lehrer~<colon>in");
+}
+
+#[test]
+fn post_process() {
+    let s = String::from("No characters to replace here");
+    let new_s = Generator::post_process(Generator::pre_process(s.clone()));
+    assert_eq!(s, new_s);
+
+    let s = String::from(r"~[Characters~] ~{to~} replace~ here~/and there~~");
+    let new_s = Generator::post_process(Generator::pre_process(s));
+    assert_eq!(&new_s, r"[Characters] {to} replace here/and there~");
+}
+
+#[test]
+fn a_bit_all() {
+    let json = r#"
+{
+   "object": ["~[lame~][f]"],
+   "main": ["~{Vous~} avez un·e[object] {object}"]
+}
+"#;
+    let mut gen = Generator::new();
+    gen.add_json(json).unwrap();
+    let s = gen.instantiate("main").unwrap();
+    assert_eq!(&s, r"{Vous} avez une [lame]");
+}
+
+#[test]
+fn seed() {
+    let json = r#"
+{
+    "hero": ["John[m]", "Olivia[f]", "Gail[n]", "Tom[m]", "Judi[f]"],
+    "job[hero]": ["sorci·er·ère", "guerri·er·ère", "voleu·r·se", "barbare", "archer/archère"],
+    "arme": ["hache[f]", "épée[f]", "gourdin[m]", "arc[m]", "masse[f]"],
+    "adjectif[arme]": ["tranchant·e", "imposant·e", "étincelant·e", "rouillé·e", "brutal·e"],
+    "description": ["{hero}, un·e[hero] {job} avec un·e[arme] {arme} {adjectif}"],
+    "main[hero]": ["Il/Elle/Iel s'appelle {hero}. {hero} est un·e {job}. Il/Elle/Iel a un·e[arme] {arme}. Ce·tte[arme]  {arme} est {adjectif}. Avec lui/elle se trouve {{description}} et {{description}}. {hero} les aime bien, c'est son crew."]
+}"#;
+    let mut gen = Generator::new();
+    gen.add_json(json).unwrap();
+    let r1 = gen.instantiate_from_seed("main", 42).unwrap();
+    let r2 = gen.instantiate_from_seed("main", 42).unwrap();
+    assert_eq!(r2, r1);
+}
+
+#[test]
+fn capitalize_1() {
+    let s = Generator::capitalize(&English, "foo", "bar");
+    assert_eq!(s, "bar");
+
+    let s = Generator::capitalize(&English, "Foo", "bar");
+    assert_eq!(s, "Bar");
+
+    let s = Generator::capitalize(&English, "FOO", "bar");
+    assert_eq!(s, "BAR");
+}
+
+#[test]
+fn capitalize_3() {
+    let mut gen = Generator::new();
+    gen.add_json(
+        r#"
+{
+    "$meta": {"language": "tr"},
+    "city": ["istanbul"],
+    "book": ["ilk aşk ve ~~iz"],
+    "main": ["{City}, {CITY}, {book.title}, {Book.title}"]
+}
+"#).unwrap();
+    assert_eq!(&gen.instantiate("main").unwrap(),
+               "İstanbul, İSTANBUL, İlk Aşk Ve ~İz, İlk Aşk Ve ~İz");
+    assert!(gen.msg("{city.upper}", &[]).is_err());
+}
+
+#[test]
+fn capitalize_sentences() {
+    let mut gen = Generator::new();
+    gen.add_json(
+        r#"
+{
+    "$meta": {"language": "fr", "contractions": true, "capitalize_sentences": true},
+    "hero": ["Alex"],
+    "arme": ["épée[f]", "~hache[f]"],
+    "phone": ["iPhone"],
+    "main[hero]": ["le/la[arme] {arme} brille... il/elle sourit ! {phone} ? « oh. » 3 fois. é\u0301té."]
+}
+"#).unwrap();
+    for i in 0..10 {
+        let s = gen.instantiate_from_seed("main", i).unwrap();
+        assert!(["L'épée brille... Il/elle sourit ! iPhone ? « Oh. » 3 fois. É\u{301}té.",
+                 "La hache brille... Il/elle sourit ! iPhone ? « Oh. » 3 fois. É\u{301}té."].contains(&s.as_str()), "{}", s);
+    }
+    gen.set_capitalize_sentences(false);
+    assert_eq!(&gen.msg("il arrive. il part.", &[]).unwrap(), "il arrive. il part.");
+}
+
+#[test]
+fn typography() {
+    let mut gen = Generator::new();
+    gen.add_json(
+        r#"
+{
+    "$meta": {"language": "fr", "typography": true},
+    "hero": ["Léa[f]"],
+    "arme": ["épée[f]"],
+    "adjectif": [""],
+    "main[hero]": ["Ce·tte[arme]  {arme} {adjectif} , dit-elle : \"{hero} ~~ l'a ~! \"; \"non\""]
+}
+"#).unwrap();
+    assert_eq!(&gen.instantiate("main").unwrap(),
+               "Cette épée, dit-elle\u{202F}: «\u{202F}Léa ~ l’a\u{202F}!\u{202F}»\u{202F}; «\u{202F}non\u{202F}»");
+    gen.set_typography(false);
+    assert_eq!(&gen.msg("a  , b", &[]).unwrap(), "a  , b");
+}
+
+#[test]
+fn capitalize_2() {
+    let mut gen = Generator::new();
+    gen.add_json(
+        r#"
+{
+    "dog": ["a good dog"],
+    "foo": ["Zyma. {Dog}"],
+    "bar": ["Zyma is {dog}"],
+    "baz": ["Zyma is {DOG}"]
+}
+"#,
+    )
+    .unwrap();
+    let foo = gen.instantiate("foo").unwrap();
+    let bar = gen.instantiate("bar").unwrap();
+    let baz = gen.instantiate("baz").unwrap();
+
+    assert_eq!(&foo, "Zyma. A good dog");
+    assert_eq!(&bar, "Zyma is a good dog");
+    assert_eq!(&baz, "Zyma is A GOOD DOG");
+}
+
+
+#[test]
+fn set() {
+    let mut gen = Generator::new();
+    gen.add_json(
+        r#"
+{
+    "name": ["Marie[f]"],
+    "foo": ["{set hero = {name}}{hero}: il/elle[hero]. {set hero = \"Jean[m]\"}{hero}: il/elle[hero]."],
+    "bar": ["{set mood = \"dark\"}{{baz}} {unset mood}{mood}"],
+    "baz": ["{mood}"],
+    "mood": ["light"],
+    "story[hero]": ["{set hero = {name}}{hero}: il/elle. {set hero = \"Jean[m]\"}Il/Elle."]
+}
+"#).unwrap();
+    assert_eq!(&gen.instantiate("foo").unwrap(), "Marie: elle. Jean: il.");
+    assert_eq!(&gen.instantiate("bar").unwrap(), "dark light");
+    // The dependency of a symbol can be defined by an action
+    assert_eq!(&gen.instantiate("story").unwrap(), "Marie: elle. Il.");
+    assert!(gen.msg("{set foo = bar}", &[]).is_err());
+}
+
+#[test]
+fn params() {
+    let mut gen = Generator::new();
+    gen.add_json(
+        r#"
+{
+    "hero": ["Jean[m]"],
+    "villain": ["Marie[f]"],
+    "greet(who)": ["hello {who}, il/elle[who] is here"],
+    "duel(a, b)[a]": ["{Greet(a)}. {greet(b)}. Il/Elle wins."],
+    "main": ["{duel(hero, villain)} {duel(villain, hero)}"],
+    "wrong": ["{greet(hero, villain)}"],
+    "none": ["{greet}"]
+}
+"#).unwrap();
+    assert_eq!(&gen.instantiate("main").unwrap(),
+               "Hello Jean, il is here. hello Marie, elle is here. Il wins. \
+                Hello Marie, elle is here. hello Jean, il is here. Elle wins.");
+    assert!(gen.instantiate("wrong").is_err());
+    assert!(gen.instantiate("none").is_err());
+    assert!(gen.add("foo(a, a)", &["{a}"]).is_err());
+
+    let json = gen.to_json().unwrap();
+    assert!(json.contains(r#""duel(a, b)[a]""#));
+
+    gen.add_json(
+        r#"
+{
+    "a": ["Ann"],
+    "b": ["Bob"],
+    "inner": ["hi {who}"],
+    "hi(who)": ["{inner}!"]
+}
+"#).unwrap();
+    assert_eq!(&gen.msg("{hi(a)} {hi(b)} {hi(a)}", &[]).unwrap(), "hi Ann! hi Bob! hi Ann!");
+}
+
+#[test]
+fn expressions() {
+    let mut gen = Generator::new();
+    gen.add_json(
+        r#"
+{
+    "gold": ["10"],
+    "name": ["Bob"],
+    "foo": ["{gold * 2 + 1} {(gold - 4) / 3} {{gold}}"],
+    "bar": ["{set gold = gold + 5}{gold} {gold * 2}"],
+    "baz": ["You find {2d20} coins and {1-6} gems"],
+    "wrong": ["{name + 1}"]
+}
+"#).unwrap();
+    assert_eq!(&gen.instantiate("foo").unwrap(), "21 2 10");
+    assert_eq!(&gen.instantiate("bar").unwrap(), "15 30");
+    assert!(gen.instantiate("wrong").is_err());
+    assert!(gen.msg("{1 / 0}", &[]).is_err());
+    for i in 0..10 {
+        let s1 = gen.instantiate_from_seed("baz", i).unwrap();
+        let s2 = gen.instantiate_from_seed("baz", i).unwrap();
+        assert_eq!(s1, s2);
+    }
+    for _ in 0..20 {
+        let n: i64 = gen.msg("{d20}", &[]).unwrap().parse().unwrap();
+        assert!((1..=20).contains(&n));
+    }
+    gen.add("d20", &["twenty"]).unwrap();
+    assert_eq!(&gen.msg("{d20}", &[]).unwrap(), "twenty");
+}
+
+#[test]
+fn words() {
+    let mut gen = Generator::new();
+    gen.add_json(
+        r#"
+{
+    "$meta": {"language": "fr"},
+    "count": ["21"],
+    "arme": ["épée[f]"],
+    "foo[arme]": ["{Count.words} {arme}s"],
+    "bar": ["{count.words[arme]} {arme}s, {(count + 1).words[arme]} {arme}s et {count.words}"],
+    "wrong": ["{arme.words}"]
+}
+"#).unwrap();
+    assert_eq!(&gen.instantiate("foo").unwrap(), "Vingt et une épées");
+    assert_eq!(&gen.instantiate("bar").unwrap(),
+               "vingt et une épées, vingt-deux épées et vingt et un/une");
+    assert!(gen.instantiate("wrong").is_err());
+    assert!(gen.msg("{count.foo}", &[]).is_err());
+    gen.set_metadata(Metadata {
+        language: Some("de".into()),
+        ..Metadata::default()
+    });
+    assert!(gen.msg("{count.words}", &[]).is_err());
+
+    let mut gen = Generator::new();
+    gen.add("n", &["21"]).unwrap();
+    assert_eq!(&gen.msg("{n.words} dogs", &[]).unwrap(), "twenty-one dogs");
+}
+
+#[test]
+fn plurals() {
+    let mut gen = Generator::new();
+    gen.add_json(
+        r#"
+{
+    "$meta": {"language": "fr"},
+    "one": ["1"],
+    "three": ["3"],
+    "hero": ["Marie[f]"],
+    "foo[#three]": ["{three} pomme(s), {one} pomme(s)[#one], {three} pomme/pommes, f~(x~), voir f(x)"],
+    "bar[hero,#three]": ["{three} sorci·er·ère(s), il/elle(s), {one} sorci·er·ère(s)[#one]"],
+    "baz[hero]": ["{three} sorci·er·ère(s)[#three] {one} œuf/œufs[#one] pomme(s)"]
+}
+"#).unwrap();
+    assert_eq!(&gen.instantiate("foo").unwrap(), "3 pommes, 1 pomme, 3 pommes, f(x), voir f(x)");
+    assert_eq!(&gen.instantiate("bar").unwrap(), "3 sorcières, elles, 1 sorcière");
+    assert_eq!(&gen.instantiate("baz").unwrap(), "3 sorcières 1 œuf pomme(s)");
+    assert_eq!(&gen.msg("{three} cheveu(x)[#three]", &[]).unwrap(), "3 cheveux");
+    assert!(gen.msg("pomme(s)[#hero]", &[]).is_err());
+    assert!(gen.msg("pomme(s)[#one,#three]", &[]).is_err());
+}
+
+#[test]
+fn language() {
+    let mut gen = Generator::new();
+    assert_eq!(gen.language().code(), "en");
+    gen.add_json(r#"{"$meta": {"language": "fr-CA"}, "n": ["2000000"], "foo": ["{n} pomme/pommes[#n]"]}"#)
+        .unwrap();
+    assert_eq!(gen.language().code(), "fr");
+    gen.set_metadata(Metadata {
+        language: Some("ru".into()),
+        ..Metadata::default()
+    });
+    assert_eq!(gen.language().code(), "ru");
+    assert_eq!(gen.language().plural_category(3), PluralCategory::Few);
+    gen.set_language(French);
+    assert_eq!(gen.language().code(), "fr");
+    assert_eq!(&gen.instantiate("foo").unwrap(), "2000000 pommes");
+}
+
+#[test]
+fn neutral_style() {
+    let mut gen = Generator::new();
+    gen.add_json(
+        r#"
+{
+    "$meta": {"language": "fr"},
+    "n": ["21"],
+    "three": ["3"],
+    "foo": ["un·e sorci·er·ère, heureu·x·se, il/elle, {n.words}"],
+    "bar[#three]": ["{three} sorci·er·ère(s)"]
+}
+"#).unwrap();
+    let mut test = |style, expected: &str| {
+        gen.set_neutral_style(style);
+        assert_eq!(&gen.instantiate("foo").unwrap(), expected);
+    };
+    test(NeutralStyle::Slash,
+         "un/une sorcier/sorcière, heureux/heureuse, il/elle, vingt et un/une");
+    test(NeutralStyle::Doublet,
+         "un ou une sorcier ou sorcière, heureux ou heureuse, il ou elle, vingt et un ou une");
+    test(NeutralStyle::MedianPoint, "un·e sorcier·ère, heureux·se, il·elle, vingt et un·e");
+    test(NeutralStyle::Parenthetical,
+         "un(e) sorcier(ère), heureux(se), il (elle), vingt et un(e)");
+    test(NeutralStyle::Masculine, "un sorcier, heureux, il, vingt et un");
+    gen.set_neutral_style(NeutralStyle::Error);
+    assert!(gen.instantiate("foo").is_err());
+    gen.set_neutral_style(NeutralStyle::Slash);
+    assert_eq!(&gen.instantiate("bar").unwrap(), "3 sorciers/sorcières");
+    assert_eq!(&gen.msg("{FOO}", &[("foo", "il/elle~ !")]).unwrap(), "IL/ELLE !");
+
+    // The style is kept in the metadata, and exported with it
+    gen.set_neutral_style(NeutralStyle::MedianPoint);
+    gen.set_group_rule(GroupRule::NeutralIfMixed);
+    let mut gen2 = Generator::new();
+    gen2.add_json(&gen.to_json().unwrap()).unwrap();
+    assert_eq!(gen2.metadata().neutral_style, Some(NeutralStyle::MedianPoint));
+    assert_eq!(gen2.metadata().group_rule, Some(GroupRule::NeutralIfMixed));
+    assert_eq!(&gen2.instantiate("foo").unwrap(),
+               "un·e sorcier·ère, heureux·se, il·elle, vingt et un·e");
+}
+
+#[test]
+fn groups() {
+    let mut gen = Generator::new();
+    gen.add_json(
+        r#"
+{
+    "$meta": {"language": "fr"},
+    "hero": ["Jean[m]"],
+    "sidekick": ["Marie[f]"],
+    "friend": ["Julie[f]"],
+    "mixed": ["ils/elles/iels[hero,sidekick] sont parti·e(s)[hero,sidekick]"],
+    "women[sidekick,friend]": ["ils/elles/iels sont parti·e(s), tou·s·tes les deux"],
+    "one[hero]": ["il/elle(s) est parti·e(s)"]
+}
+"#).unwrap();
+    assert_eq!(&gen.instantiate("mixed").unwrap(), "ils sont partis");
+    assert_eq!(&gen.instantiate("women").unwrap(), "elles sont parties, toutes les deux");
+    assert_eq!(&gen.instantiate("one").unwrap(), "il(s) est parti(s)");
+    gen.set_group_rule(GroupRule::Masculine);
+    assert_eq!(&gen.instantiate("women").unwrap(), "elles sont parties, toutes les deux");
+    assert_eq!(&gen.instantiate("mixed").unwrap(), "ils sont partis");
+    gen.set_group_rule(GroupRule::NeutralIfMixed);
+    assert_eq!(&gen.instantiate("mixed").unwrap(), "iels sont partis/parties");
+}
+
+#[test]
+fn pronouns() {
+    let mut gen = Generator::new();
+    gen.add_json(
+        r#"
+{
+    "$meta": {
+        "language": "fr",
+        "pronouns": {
+            "iel": {"subject": "iel", "object": "ellui", "possessive": "son",
+                    "reflexive": "se", "agreement": "n"},
+            "elle": {"subject": "elle", "object": "la", "possessive": "son",
+                     "reflexive": "se", "agreement": "f"}
+        }
+    },
+    "hero": ["Alex[pronouns:iel]"],
+    "sidekick": ["Sam[pronouns:elle]"],
+    "villain": ["Igor[m]"],
+    "stranger": ["Dominique"],
+    "main": ["{Hero:subj} est parti·e[hero]. {Sidekick:subj} est parti·e[sidekick]."],
+    "others": ["{villain:subj} {stranger:subj} {villain:obj}"]
+}
+"#).unwrap();
+    assert_eq!(&gen.instantiate("main").unwrap(), "Iel est parti/partie. Elle est partie.");
+    assert_eq!(&gen.instantiate("others").unwrap(), "il il/elle le");
+    assert!(gen.msg("{hero:them}", &[]).is_err());
+    assert!(gen.to_json().unwrap().contains("ellui"));
+
+    let mut gen = Generator::new();
+    gen.add("hero", &["Alex[pronouns:xe]"]).unwrap();
+    assert!(gen.instantiate("hero").is_err());
+    gen.add_pronouns("xe", PronounSet::new("xe", "xem", "xyr", "xemself", Gender::Neutral));
+    gen.add("villain", &["Igor[m]", "Ada[f]"]).unwrap();
+    gen.add("main", &["{HERO:subj}, {villain:subj}, {hero:obj}."]).unwrap();
+    let s = gen.instantiate("main").unwrap();
+    assert!(s == "XE, he, xem." || s == "XE, she, xem.");
+    assert!(gen.add("other", &["Alex[pronouns:xe][m]"]).is_ok());
+    assert!(gen.instantiate("other").is_err());
+}
+
 #[test]
-fn add_1() {
+fn gender_classes() {
     let mut gen = Generator::new();
-    gen.add("Test", &["foo", "bar"]).unwrap();
+    gen.add_json(
+        r#"
+{
+    "$meta": {"language": "de", "genders": ["m", "f", "nt"]},
+    "man": ["Mann[m]"],
+    "woman": ["Frau[f]"],
+    "book": [{"text": "Buch", "gender": "nt"}],
+    "thing": ["Ding"],
+    "main": ["der/die/das[book] {book}, der/die/das[man] {man}, der/die/das[woman] {woman}"],
+    "neutral": ["er/sie/es[thing] er/sie/es/xier[thing]"],
+    "too_many": ["a/b/c/d/e[book]"],
+    "too_few": ["er/sie[book]"]
+}
+"#).unwrap();
+    assert_eq!(&gen.instantiate("main").unwrap(), "das Buch, der Mann, die Frau");
+    assert_eq!(&gen.instantiate("neutral").unwrap(), "er/sie/es xier");
+    assert!(gen.instantiate("too_many").is_err());
+    assert!(gen.instantiate("too_few").is_err());
+    let path = "see /usr/local/share/doc";
+    assert_eq!(&gen.msg(path, &[]).unwrap(), path);
+    assert_eq!(&Generator::new().msg(path, &[]).unwrap(), path);
+    assert!(gen.to_json().unwrap().contains(r#""gender": "nt""#));
+    assert!(gen.add_json(r#"{"x": [{"text": "x", "gender": "n"}]}"#).is_err());
+
+    // Pronoun sets can agree with a gender class, which is exported by name
+    gen.add_json(r#"{"$meta": {"genders": ["m", "f", "nt"], "pronouns": {"es": {
+        "subject": "es", "object": "es", "possessive": "sein", "reflexive": "sich",
+        "agreement": "nt"}}}, "kind": ["Kind[pronouns:es]"]}"#).unwrap();
+    assert_eq!(gen.metadata().pronouns["es"].agreement, Gender::Class(2));
+    gen.add_pronouns("xier", PronounSet::new("xier", "xien", "xieser", "sich", Gender::Class(2)));
+    let json = gen.to_json().unwrap();
+    assert!(json.contains(r#""agreement": "nt""#));
+    let mut gen2 = Generator::new();
+    gen2.add_json(&json).unwrap();
+    assert_eq!(gen2.metadata().pronouns["xier"].agreement, Gender::Class(2));
+    assert_eq!(&gen2.msg("der/die/das[kind] {kind}", &[]).unwrap(), "das Kind");
+    assert!(gen.add_json(r#"{"$meta": {"genders": ["m", "f"], "pronouns": {"es": {
+        "subject": "es", "object": "es", "possessive": "sein", "reflexive": "sich",
+        "agreement": "nt"}}}}"#).is_err());
+
+    let mut gen = Generator::new();
+    gen.set_gender_classes(&["m1", "m2", "ki", "n"]);
+    gen.add("child", &["mtoto[m1]"]).unwrap();
+    gen.add("book", &["kitabu[ki]"]).unwrap();
+    gen.add("main", &["{child} a/wa/ki/i[child]mesoma. {book} ki/i[book]mesomwa"]).unwrap();
+    assert!(gen.instantiate("main").is_err());
+    gen.add("main", &["{child} a/wa/ki/i[child]mesoma, {book} a/wa/ki/i[book]mesomwa"]).unwrap();
+    assert_eq!(&gen.instantiate("main").unwrap(), "mtoto amesoma, kitabu kimesomwa");
 }
 
 #[test]
-fn add_json() {
+fn referent() {
     let mut gen = Generator::new();
     gen.add_json(
         r#"
 {
-   "Test": ["foo", "bar"]
-}"#,
-    )
-    .unwrap();
+    "$meta": {"language": "fr"},
+    "person": ["la sentinelle[f][ref:m]"],
+    "doctor": [{"text": "le médecin", "gender": "m", "referent": "f"}],
+    "main[person]": ["{Person} est arrivé·e. Il/Elle[person:ref] est fatigué·e[person:ref]."],
+    "other": ["{doctor} est venu·e[doctor], {doctor:subj} est content·e[doctor:ref]."],
+    "group": ["Ils/Elles[person:ref,doctor:ref] sont là."]
+}
+"#).unwrap();
+    assert_eq!(&gen.instantiate("main").unwrap(),
+               "La sentinelle est arrivée. Il est fatigué.");
+    assert_eq!(&gen.instantiate("other").unwrap(),
+               "le médecin est venu, elle est contente.");
+    assert_eq!(&gen.instantiate("group").unwrap(), "Ils sont là.");
+    assert!(gen.to_json().unwrap().contains(r#""referent": "f""#));
+    assert!(gen.msg("il/elle[person:foo]", &[]).is_err());
+    gen.add("twice", &["x[ref:m][ref:f]"]).unwrap();
+    assert!(gen.instantiate("twice").is_err());
 }
 
 #[test]
-fn missing_symbol() {
-    let gen = Generator::new();
-    assert!(gen.instantiate("foo").is_err());
+fn contractions() {
+    let mut gen = Generator::new();
+    gen.add_json(
+        r#"
+{
+    "$meta": {"language": "fr", "contractions": true},
+    "arme": ["épée[f]"],
+    "hero": ["~héros"],
+    "verbe": ["aime"],
+    "main": ["Le/La[arme] {arme} de le {hero}. Je {verbe} à les {hero} de~ le faire."],
+    "upper": ["{Hero}"]
+}
+"#).unwrap();
+    assert_eq!(&gen.instantiate("main").unwrap(),
+               "L'épée du héros. J'aime aux héros de le faire.");
+    assert_eq!(&gen.msg("Il a décidé de le faire, et commence à les voir.", &[]).unwrap(),
+               "Il a décidé de le faire, et commence à les voir.");
+    assert_eq!(&gen.msg("Prends-le {arme} avec toi.", &[]).unwrap(),
+               "Prends-le épée avec toi.");
+    assert_eq!(&gen.msg("Prends-le avec toi.", &[]).unwrap(), "Prends-le avec toi.");
+    assert_eq!(&gen.instantiate("upper").unwrap(), "Héros");
+    assert!(gen.to_json().unwrap().contains("~héros"));
+    gen.set_contractions(false);
+    assert_eq!(&gen.msg("le arbre", &[]).unwrap(), "le arbre");
 }
 
 #[test]
-fn replacement_1() {
+fn articles() {
     let mut gen = Generator::new();
-    gen.add("foo", &["hello"]).unwrap();
-    gen.add("bar", &["{foo} world"]).unwrap();
-    assert_eq!(gen.instantiate("bar").unwrap(), String::from("hello world"));
+    gen.add_json(
+        r#"
+{
+    "animal": ["unicorn"],
+    "time": ["hour"],
+    "fruit": [{"text": "apple", "tags": ["red"]}, {"text": "banana", "tags": ["yellow"]}],
+    "main": ["{A:animal}, {a:time}, {an:fruit#red}, {{a:Fruit#yellow}} and {{a:animal}}."]
 }
+"#).unwrap();
+    assert_eq!(&gen.instantiate("main").unwrap(),
+               "A unicorn, an hour, an apple, a Banana and a unicorn.");
+    assert!(gen.msg("{a:foo}", &[]).is_err());
+    gen.add("a", &["Alex[m]"]).unwrap();
+    assert_eq!(&gen.msg("{a:subj}", &[]).unwrap(), "he");
+    gen.set_contractions(true);
+    assert_eq!(&gen.msg("a {time} and an {animal}", &[]).unwrap(), "an hour and a unicorn");
+    assert_eq!(&gen.msg("The letter a is first, an {time} later.", &[]).unwrap(),
+               "The letter a is first, an hour later.");
 
-#[test]
-fn replacement_2() {
     let mut gen = Generator::new();
-    gen.add("foo", &["hello"]).unwrap();
-    gen.add("bar", &["world"]).unwrap();
-    gen.add("baz", &["{foo} {bar}"]).unwrap();
-    assert_eq!(gen.instantiate("baz").unwrap(), String::from("hello world"));
+    gen.add_json(
+        r#"
+{
+    "$meta": {"language": "fr"},
+    "arme": ["épée[f]"],
+    "ami": ["ami"],
+    "main": ["{Un:arme} et {un:ami}"]
+}
+"#).unwrap();
+    assert!(gen.instantiate("main").is_err());
+    gen.add("main", &["{A:arme} et {a:ami}"]).unwrap();
+    gen.set_neutral_style(NeutralStyle::MedianPoint);
+    assert_eq!(&gen.instantiate("main").unwrap(), "Une épée et un·e ami");
+    gen.set_metadata(Metadata {
+        language: Some("ru".into()),
+        ..Metadata::default()
+    });
+    assert!(gen.instantiate("main").is_err());
 }
 
 #[test]
-fn gender_1() {
+fn inclusive() {
     let mut gen = Generator::new();
-    gen.add("foo[plop]", &["He/She is happy"]).unwrap();
-    gen.set_gender("plop", Gender::Male);
-    assert_eq!(&gen.instantiate("foo").unwrap(), "He is happy");
-    gen.set_gender("plop", Gender::Female);
-    assert_eq!(&gen.instantiate("foo").unwrap(), "She is happy");
+    gen.add_json(
+        r#"
+{
+    "$meta": {"language": "es"},
+    "hero": ["Juan[m]", "Ana[f]", "Alex"],
+    "main": ["{hero} y sus amig@s[hero]: el/la[hero] chicx[hero] y los/las[hero] amigos/as[hero]."],
+    "email": ["foo@bar.com"]
 }
+"#).unwrap();
+    for i in 0..10 {
+        let s = gen.instantiate_from_seed("main", i).unwrap();
+        assert!(["Juan y sus amigos: el chico y los amigos.",
+                 "Ana y sus amigas: la chica y las amigas.",
+                 "Alex y sus amigues: le chique y les amigues."].contains(&s.as_str()), "{}", s);
+    }
+    assert_eq!(&gen.instantiate("email").unwrap(), "foo@bar.com");
+    gen.add("hero", &["Ana[f]"]).unwrap();
+    assert_eq!(&gen.msg("Les[hero] amigues[hero] y les amigues", &[]).unwrap(),
+               "Las amigas y les amigues");
+    assert_eq!(&gen.msg("Mi ex es fénix y lux, amigx[hero]", &[]).unwrap(),
+               "Mi ex es fénix y lux, amiga");
+    gen.set_language(crate::Portuguese::default());
+    assert_eq!(&gen.msg("o xerox e o durex", &[]).unwrap(), "o xerox e o durex");
+    gen.set_language(Spanish::with_ending(crate::InclusiveEnding::At));
+    gen.add("hero", &["Alex"]).unwrap();
+    assert_eq!(&gen.msg("l@s amigxs[hero]", &[]).unwrap(), "l@s amig@s");
 
-#[test]
-fn gender_2() {
     let mut gen = Generator::new();
-    gen.add("plop", &["Joe[m]"]).unwrap();
-    gen.add("foo[plop]", &["He/She is happy"]).unwrap();
-    assert_eq!(&gen.instantiate("foo").unwrap(), "He is happy");
+    gen.add_json(
+        r#"
+{
+    "$meta": {"language": "pt-BR"},
+    "hero": ["Alex"],
+    "main[hero]": ["O/A amig@ dele/dela"]
+}
+"#).unwrap();
+    assert_eq!(&gen.instantiate("main").unwrap(), "Ê amigue delu");
 }
 
 #[test]
-fn gender_3() {
+fn markers() {
     let mut gen = Generator::new();
-    gen.add("plop", &["Joe[m]"]).unwrap();
-    gen.add("foo", &["He/She[plop] is happy"]).unwrap();
-    assert_eq!(&gen.instantiate("foo").unwrap(), "He is happy");
+    gen.add_json(
+        r#"
+{
+    "$meta": {"language": "fr", "markers": ["period", "parentheses", "hyphen"]},
+    "hero": ["Jean[m]", "Léa[f]"],
+    "main[hero]": ["Les étudiant.e.s sont heureux.se.s, les act-eur-rice(s) et l'ami(e) sportif(ve)."],
+    "other": ["Peut-être ici.Voir les pomme(s) sur www.exemple.fr"]
 }
+"#).unwrap();
+    for i in 0..10 {
+        let s = gen.instantiate_from_seed("main", i).unwrap();
+        assert!(["Les étudiants sont heureux, les act-eur-rice(s) et l'ami sportif.",
+                 "Les étudiantes sont heureuses, les act-eur-rice(s) et l'amie sportive."].contains(&s.as_str()), "{}", s);
+    }
+    assert_eq!(&gen.instantiate("other").unwrap(), "Peut-être ici.Voir les pomme(s) sur www.exemple.fr");
+    gen.add("hero", &["Alex"]).unwrap();
+    assert_eq!(&gen.msg("les acteur.rice.s[hero] et directeur-rice-s[hero]", &[]).unwrap(),
+               "les acteurs/actrices et directeurs/directrices");
+    assert_eq!(&gen.msg("Dis-le, fais-le et rends-le-moi.", &[]).unwrap(),
+               "Dis-le, fais-le et rends-le-moi.");
+    gen.add("hero", &["Léa[f]"]).unwrap();
+    gen.add("imperative[hero]", &["Dis-le, rends-le-moi : nul-le, citoyen-ne-s, muet.te"]).unwrap();
+    assert_eq!(&gen.instantiate("imperative").unwrap(),
+               "Dis-le, rends-le-moi : nulle, citoyennes, muette");
 
-#[test]
-fn gender_4() {
     let mut gen = Generator::new();
-    gen.add("arme", &["batte[f]"]).unwrap();
-    gen.add("foo", &["Un homme au/à~ la[arme] {arme} facile"])
-        .unwrap();
-    assert_eq!(
-        &gen.instantiate("foo").unwrap(),
-        "Un homme à la batte facile"
-    );
+    gen.add_json(r#"{"$meta": {"language": "fr"}, "hero": ["Léa[f]"], "main[hero]": ["l'ami.e"]}"#).unwrap();
+    assert_eq!(&gen.instantiate("main").unwrap(), "l'ami.e");
 }
 
 #[test]
-fn cyclic() {
+fn strict() {
     let mut gen = Generator::new();
-    let json = r#"
+    gen.add_json(
+        r#"
 {
-   "a[b]": ["Foo"],
-   "b[a]": ["Bar"]
-}"#;
-    gen.add_json(json).unwrap();
-    assert!(gen.instantiate("a").is_err());
+    "$meta": {"markers": ["explicit", "inclusive", "median_point", "slash"]},
+    "hero": ["Jean[m]", "Léa[f]", "Alex"],
+    "n": ["3"],
+    "main[hero]": ["<Il est|Elle est|Iel est> là, avec <une pomme|des pommes>[#n] et <un|une> ami·e."],
+    "other[hero]": ["Le 12/04, and/or 1/2 de la col·lecció, amig@s, <il|elle>."]
+}
+"#).unwrap();
+    for i in 0..10 {
+        let s = gen.instantiate_from_seed("main", i).unwrap();
+        assert!(["Il est là, avec des pommes et un ami.",
+                 "Elle est là, avec des pommes et une amie.",
+                 "Iel est là, avec des pommes et un/une ami/amie."].contains(&s.as_str()), "{}", s);
+    }
+    gen.add("hero", &["Léa[f]"]).unwrap();
+    assert_eq!(&gen.instantiate("other").unwrap(), "Le 04, or 2 de la collecció, amig@s, elle.");
+    gen.set_markers(&[Marker::Explicit]);
+    assert_eq!(&gen.instantiate("other").unwrap(),
+               "Le 12/04, and/or 1/2 de la col·lecció, amig@s, elle.");
+    assert!(gen.msg("<a|b|c|d>[hero]", &[]).is_err());
+    gen.set_markers(&[]);
+    assert_eq!(&gen.msg("<il|elle> a/b", &[]).unwrap(), "<il|elle> a/b");
+    gen.set_markers(&[Marker::Explicit]);
+    assert_eq!(&gen.msg("~<a~|b> <a~|b|c>[hero]", &[]).unwrap(), "<a|b> c");
+    assert!(gen.to_json().unwrap().contains(r#""markers": ["#));
+    assert_eq!(gen.metadata().markers, Some(vec![Marker::Explicit]));
+
+    // Explicit expressions must be enabled
+    let mut gen = Generator::new();
+    gen.add("hero", &["Léa[f]"]).unwrap();
+    assert_eq!(&gen.msg("<il|elle>[hero] il/elle[hero]", &[]).unwrap(),
+               "<il|elle>[hero] elle");
 }
 
 #[test]
-fn unexisting() {
+fn german() {
     let mut gen = Generator::new();
-    let json = r#"
+    gen.add_json(
+        r#"
 {
-   "a[b]": ["Foo"],
-   "b[c]": ["Bar"]
-}"#;
-    gen.add_json(json).unwrap();
-    assert!(gen.instantiate("a").is_err());
+    "$meta": {"language": "de", "genders": ["m", "f", "n"]},
+    "hero": ["Jonas[m]", "Lena[f]", "Alex"],
+    "buch": ["Buch[n]"],
+    "main[hero]": ["{der:hero} Lehrer*in gibt {der.dat.pl:hero} Schüler:innen {ein.akk:buch}."],
+    "other[hero]": ["{Ein:hero} KollegIn von {der.dat:hero} Lehrer_in"]
+}
+"#).unwrap();
+    for i in 0..10 {
+        let s = gen.instantiate_from_seed("main", i).unwrap();
+        assert!(["der Jonas Lehrer gibt den Jonas Schüler ein Buch.",
+                 "die Lena Lehrerin gibt den Lena Schülerinnen ein Buch.",
+                 "der*die Alex Lehrer*in gibt den Alex Schüler*innen ein Buch."].contains(&s.as_str()), "{}", s);
+    }
+    gen.add("hero", &["Alex"]).unwrap();
+    gen.set_language(German::with_marker(crate::GermanMarker::BinnenI));
+    assert_eq!(&gen.instantiate("other").unwrap(),
+               "Ein/eine Alex KollegIn von dem/der Alex LehrerIn");
+    assert!(gen.msg("{der.foo:hero}", &[]).is_err());
+    assert!(gen.msg("{ein.pl:buch}", &[]).is_err());
+    gen.add("der", &["Jonas[m]"]).unwrap();
+    assert_eq!(&gen.msg("{der:subj} und {hero:subj}", &[]).unwrap(), "er und er/sie");
 }
 
 #[test]
-fn pre_process() {
-    let s = Generator::pre_process(r"foobarbaz".to_string());
-    assert_eq!(&s, "foobarbaz");
-
-    let s = Generator::pre_process(r"~foobarbaz".to_string());
-    assert_eq!(&s, "foobarbaz");
+fn msg() {
+    let mut gen = Generator::new();
+    gen.add_json(
+        r#"
+{
+    "dog": ["a good dog"]
+}
+"#).unwrap();
+    let result = gen.msg("This is {DOG}", &[]).unwrap();
+    assert_eq!(&result, "This is A GOOD DOG");
 
-    let s = Generator::pre_process(r"~~foobarbaz".to_string());
-    assert_eq!(&s, r"~<tilde>foobarbaz");
+    let result = gen.msg("{doggo} is {DOG}, he/she[doggo] is so cute!", &[("doggo", "Zyma[f]")]).unwrap();
+    assert_eq!(&result, "Zyma is A GOOD DOG, she is so cute!");
+}
 
-    let s = Generator::pre_process(r"foo~ bar~ baz".to_string());
-    assert_eq!(&s, r"foo~<space>bar~<space>baz");
+#[test]
+fn add_json_objects() {
+    let mut gen = Generator::new();
+    gen.add_json(
+        r#"
+{
+    "arme": [{"text": "épée", "gender": "f"}],
+    "main": {"depends_on": "arme",
+             "alternatives": [{"text": "Un·e {arme}", "tags": ["foo"]}]}
+}
+"#).unwrap();
+    assert_eq!(&gen.instantiate("main").unwrap(), "Une épée");
 
-    let s = Generator::pre_process(r"~[foobarbaz~]".to_string());
-    assert_eq!(&s, r"~<leftsquare>foobarbaz~<rightsquare>");
+    let mut gen = Generator::new();
+    assert!(gen.add_json(r#"{"arme": [{"text": "épée", "gender": "x"}]}"#).is_err());
+    assert!(gen.add_json(r#"{"a[b]": {"depends_on": "c", "alternatives": []}}"#).is_err());
+}
 
-    let s = Generator::pre_process(r"~{foobarbaz~}".to_string());
-    assert_eq!(&s, r"~<leftcurly>foobarbaz~<rightcurly>");
+#[test]
+fn weights() {
+    let mut gen = Generator::new();
+    gen.add_json(
+        r#"
+{
+    "foo": [{"text": "never", "weight": 0}, {"text": "always", "weight": 2}]
+}
+"#).unwrap();
+    for i in 0..10 {
+        assert_eq!(&gen.instantiate_from_seed("foo", i).unwrap(), "always");
+    }
+}
 
-    let s = Generator::pre_process(r"foo/bar/baz".to_string());
-    assert_eq!(&s, r"foo/bar/baz");
+#[test]
+fn tags() {
+    let mut gen = Generator::new();
+    gen.add_json(
+        r#"
+{
+    "arme": [{"text": "épée", "tags": ["sharp", "long"]},
+             {"text": "dague", "tags": ["sharp"]},
+             "gourdin"],
+    "foo": ["{arme#sharp#long}"],
+    "bar": ["{arme#blunt}"]
+}
+"#).unwrap();
+    assert_eq!(&gen.instantiate("foo").unwrap(), "épée");
+    assert!(gen.instantiate("bar").is_err());
+}
 
-    let s = Generator::pre_process(r"foo~/bar~/baz".to_string());
-    assert_eq!(&s, r"foo~<slash>bar~<slash>baz");
+#[test]
+fn metadata() {
+    let mut gen = Generator::new();
+    gen.add_json(
+        r#"
+{
+    "$comment": ["This is", "a comment"],
+    "$meta": {"language": "fr", "entry": "main"},
+    "main": {"$comment": "this too", "alternatives": [{"text": "foo", "$comment": "and this"}]}
+}
+"#).unwrap();
+    assert_eq!(gen.metadata().language.as_deref(), Some("fr"));
+    assert_eq!(gen.metadata().entry.as_deref(), Some("main"));
+    assert_eq!(gen.metadata().author, None);
+    assert_eq!(&gen.instantiate("main").unwrap(), "foo");
 
-    let s = Generator::pre_process(r"foo~·bar~·baz".to_string());
-    assert_eq!(&s, r"foo~<median>bar~<median>baz");
+    assert!(gen.add_json(r#"{"$foo": ["bar"]}"#).is_err());
+    assert!(gen.add_json(r#"{"$meta": {"langage": "fr"}}"#).is_err());
 }
 
 #[test]
-fn post_process() {
-    let s = String::from("No characters to replace here");
-    let new_s = Generator::post_process(Generator::pre_process(s.clone()));
-    assert_eq!(s, new_s);
+fn add_tracery_json() {
+    let mut gen = Generator::new();
+    gen.add_tracery_json(r##"{"origin": ["#animal.capitalize# and #animal#"], "animal": "cat/dog"}"##)
+        .unwrap();
+    assert_eq!(gen.metadata().entry.as_deref(), Some("origin"));
+    assert_eq!(&gen.instantiate("origin").unwrap(), "Cat/dog and cat/dog");
+    assert!(gen.add_tracery_json(r##"{"origin": ["#animal.s#"]}"##).is_err());
 
-    let s = String::from(r"~[Characters~] ~{to~} replace~ here~/and there~~");
-    let new_s = Generator::post_process(Generator::pre_process(s));
-    assert_eq!(&new_s, r"[Characters] {to} replace here/and there~");
+    let mut gen = Generator::new();
+    gen.add_tracery_json(r##"{"origin": ["#[hero:#name#]story#"], "name": "Bob", "story": "#hero# and #hero#"}"##)
+        .unwrap();
+    assert_eq!(&gen.instantiate("origin").unwrap(), "Bob and Bob");
+
+    // Special characters are left as they are
+    let text = "F(x) and <a|b>, amig@s, Lehrer*in:innen, x = y. Hi.";
+    let tracery = format!(r##"{{"origin": ["{} #animal.a#."], "animal": "owl"}}"##, text);
+    let mut gen = Generator::new();
+    gen.add_tracery_json(&tracery).unwrap();
+    assert_eq!(gen.instantiate("origin").unwrap(), format!("{} an owl.", text));
+    let mut gen = Generator::new();
+    gen.add_json(&crate::tracery_to_json(&tracery).unwrap()).unwrap();
+    assert_eq!(gen.instantiate("origin").unwrap(), format!("{} an owl.", text));
 }
 
-#[test]
-fn a_bit_all() {
-    let json = r#"
+#[cfg(test)]
+const ROUND_TRIP: &str = r#"
 {
-   "object": ["~[lame~][f]"],
-   "main": ["~{Vous~} avez un·e[object] {object}"]
+    "$meta": {"language": "fr", "entry": "main"},
+    "arme": [{"text": "épée", "gender": "f", "weight": 3, "tags": ["sharp"]}, "gourdin[m]"],
+    "main[arme]": ["~{Un·e~} {arme} ~/ un~·e~ ~~", "Un/Une~ deux[arme]"]
 }
 "#;
+
+#[test]
+fn to_json() {
     let mut gen = Generator::new();
-    gen.add_json(json).unwrap();
-    let s = gen.instantiate("main").unwrap();
-    assert_eq!(&s, r"{Vous} avez une [lame]");
+    gen.add_json(ROUND_TRIP).unwrap();
+    let json = gen.to_json().unwrap();
+    let mut gen2 = Generator::new();
+    gen2.add_json(&json).unwrap();
+    assert_eq!(gen2.to_json().unwrap(), json);
+    assert_eq!(gen2.metadata(), gen.metadata());
+    for i in 0..10 {
+        assert_eq!(gen.instantiate_from_seed("main", i).unwrap(),
+                   gen2.instantiate_from_seed("main", i).unwrap());
+    }
 }
 
+#[cfg(feature = "yaml")]
 #[test]
-fn seed() {
-    let json = r#"
-{
-    "hero": ["John[m]", "Olivia[f]", "Gail[n]", "Tom[m]", "Judi[f]"],
-    "job[hero]": ["sorci·er·ère", "guerri·er·ère", "voleu·r·se", "barbare", "archer/archère"],
-    "arme": ["hache[f]", "épée[f]", "gourdin[m]", "arc[m]", "masse[f]"],
-    "adjectif[arme]": ["tranchant·e", "imposant·e", "étincelant·e", "rouillé·e", "brutal·e"],
-    "description": ["{hero}, un·e[hero] {job} avec un·e[arme] {arme} {adjectif}"],
-    "main[hero]": ["Il/Elle/Iel s'appelle {hero}. {hero} est un·e {job}. Il/Elle/Iel a un·e[arme] {arme}. Ce·tte[arme]  {arme} est {adjectif}. Avec lui/elle se trouve {{description}} et {{description}}. {hero} les aime bien, c'est son crew."]
-}"#;
+fn to_yaml() {
     let mut gen = Generator::new();
-    gen.add_json(json).unwrap();
-    let r1 = gen.instantiate_from_seed("main", 42).unwrap();
-    let r2 = gen.instantiate_from_seed("main", 42).unwrap();
-    assert_eq!(r2, r1);
+    gen.add_json(ROUND_TRIP).unwrap();
+    let mut gen2 = Generator::new();
+    gen2.add_yaml(&gen.to_yaml().unwrap()).unwrap();
+    assert_eq!(gen2.to_json().unwrap(), gen.to_json().unwrap());
 }
 
+#[cfg(feature = "toml")]
 #[test]
-fn capitalize_1() {
-    let s = Generator::capitalize("foo", "bar");
-    assert_eq!(s, "bar");
-
-    let s = Generator::capitalize("Foo", "bar");
-    assert_eq!(s, "Bar");
+fn to_toml() {
+    let mut gen = Generator::new();
+    gen.add_json(ROUND_TRIP).unwrap();
+    let mut gen2 = Generator::new();
+    gen2.add_toml(&gen.to_toml().unwrap()).unwrap();
+    assert_eq!(gen2.to_json().unwrap(), gen.to_json().unwrap());
+}
 
-    let s = Generator::capitalize("FOO", "bar");
-    assert_eq!(s, "BAR");
+#[cfg(feature = "ron")]
+#[test]
+fn to_ron() {
+    let mut gen = Generator::new();
+    gen.add_json(ROUND_TRIP).unwrap();
+    let mut gen2 = Generator::new();
+    gen2.add_ron(&gen.to_ron().unwrap()).unwrap();
+    assert_eq!(gen2.to_json().unwrap(), gen.to_json().unwrap());
 }
 
+#[cfg(feature = "yaml")]
 #[test]
-fn capitalize_2() {
+fn add_yaml() {
     let mut gen = Generator::new();
-    gen.add_json(
+    gen.add_yaml(
         r#"
-{
-    "dog": ["a good dog"],
-    "foo": ["Zyma. {Dog}"],
-    "bar": ["Zyma is {dog}"],
-    "baz": ["Zyma is {DOG}"]
+# Comments are allowed
+arme: ["épée[f]"]
+main:
+  depends_on: arme
+  alternatives:
+    - text: "Un·e {arme}"
+      weight: 2
+"#).unwrap();
+    assert_eq!(&gen.instantiate("main").unwrap(), "Une épée");
 }
-"#,
-    )
-    .unwrap();
-    let foo = gen.instantiate("foo").unwrap();
-    let bar = gen.instantiate("bar").unwrap();
-    let baz = gen.instantiate("baz").unwrap();
 
-    assert_eq!(&foo, "Zyma. A good dog");
-    assert_eq!(&bar, "Zyma is a good dog");
-    assert_eq!(&baz, "Zyma is A GOOD DOG");
+#[cfg(feature = "toml")]
+#[test]
+fn add_toml() {
+    let mut gen = Generator::new();
+    gen.add_toml(
+        r#"
+# Comments are allowed
+arme = ["épée[f]"]
+"main[arme]" = ["""
+Un·e {arme}"""]
+"#).unwrap();
+    assert_eq!(&gen.instantiate("main").unwrap(), "Une épée");
 }
 
-
+#[cfg(feature = "ron")]
 #[test]
-fn msg() {
+fn add_ron() {
     let mut gen = Generator::new();
-    gen.add_json(
+    gen.add_ron(
         r#"
+// Comments are allowed
 {
-    "dog": ["a good dog"]
+    "$meta": (language: Some("fr"), entry: Some("main")),
+    "$comment": "not a symbol",
+    "arme": [{"text": "épée", "gender": "f"}],
+    "main": {"depends_on": "arme", "alternatives": ["Un·e {arme}"]},
 }
 "#).unwrap();
-    let result = gen.msg("This is {DOG}", &[]).unwrap();
-    assert_eq!(&result, "This is A GOOD DOG");
-
-    let result = gen.msg("{doggo} is {DOG}, he/she[doggo] is so cute!", &[("doggo", "Zyma[f]")]).unwrap();
-    assert_eq!(&result, "Zyma is A GOOD DOG, she is so cute!");
+    assert_eq!(&gen.instantiate("main").unwrap(), "Une épée");
+    assert_eq!(gen.metadata().entry.as_deref(), Some("main"));
 }
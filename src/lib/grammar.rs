@@ -0,0 +1,344 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with
+// this file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Data model of a grammar file, shared by `add_json`, `to_json` and the other formats.
+//!
+//! Both the compact form (a symbol is a list of strings) and the object form
+//! (a symbol or an alternative is an object with explicit fields) are accepted.
+//! Keys starting with `$` are reserved: `$meta` contains the metadata of the grammar,
+//! and `$comment` can be used (at the top level, or in objects, where unknown fields are
+//! ignored) to write comments.
+
+use crate::generator::{Gender, GroupRule, NeutralStyle};
+use crate::pronouns::PronounSet;
+
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+
+use std::fmt;
+
+use serde::de::{IgnoredAny, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// A whole grammar: metadata, comments, and symbols with their definitions
+#[derive(Debug, Default, Serialize)]
+pub struct Grammar {
+    #[serde(
+        rename = "$meta",
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_meta"
+    )]
+    pub meta: Option<Metadata>,
+    #[serde(flatten)]
+    pub symbols: BTreeMap<String, Symbol>,
+}
+
+/// Writes the metadata as it is read, e.g. without `Some(...)` in RON
+fn serialize_meta<S: Serializer>(
+    meta: &Option<Metadata>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match meta {
+        Some(meta) => meta.serialize(serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Reads the reserved keys and the symbols from a single map, rather than with
+/// `#[serde(flatten)]`, which not all formats support
+impl<'de> Deserialize<'de> for Grammar {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct GrammarVisitor;
+
+        impl<'de> Visitor<'de> for GrammarVisitor {
+            type Value = Grammar;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a map of symbols")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Grammar, A::Error> {
+                let mut grammar = Grammar::default();
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "$meta" => grammar.meta = Some(map.next_value()?),
+                        // Comments are not symbols
+                        "$comment" => {
+                            map.next_value::<IgnoredAny>()?;
+                        }
+                        _ => {
+                            let symbol = map.next_value()?;
+                            grammar.symbols.insert(key, symbol);
+                        }
+                    }
+                }
+                Ok(grammar)
+            }
+        }
+
+        deserializer.deserialize_map(GrammarVisitor)
+    }
+}
+
+/// Metadata of a grammar.
+///
+/// This is set with the `"$meta"` key of a grammar file, e.g.:
+///
+/// ```json
+/// "$meta": {
+///     "language": "fr",
+///     "author": "Élisabeth Henry",
+///     "version": "1.0",
+///     "entry": "main",
+///     "markers": ["median_point", "slash", "period"],
+///     "neutral_style": "median_point",
+///     "pronouns": {
+///         "xe": {"subject": "xe", "object": "xem", "possessive": "xyr",
+///                "reflexive": "xemself", "agreement": "n"}
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "RawMetadata", into = "RawMetadata")]
+#[non_exhaustive]
+pub struct Metadata {
+    /// Language of the grammar, e.g. "fr" or "en"
+    pub language: Option<String>,
+    /// Author of the grammar
+    pub author: Option<String>,
+    /// Version of the grammar
+    pub version: Option<String>,
+    /// Symbol to instantiate by default
+    pub entry: Option<String>,
+    /// Pronoun sets, by name (in a grammar file, their agreement can be one of `genders`)
+    pub pronouns: BTreeMap<String, PronounSet>,
+    /// Gender classes, if the grammar doesn't use the built-in `m`, `f` and `n` genders
+    pub genders: Vec<String>,
+    /// Whether contractions, such as French elision, are applied (they aren't by default)
+    pub contractions: Option<bool>,
+    /// Whether the first letter of each sentence is capitalized (it isn't by default)
+    pub capitalize_sentences: Option<bool>,
+    /// Whether typographic conventions, such as curly quotes, are applied (they aren't by
+    /// default)
+    pub typography: Option<bool>,
+    /// Syntaxes for gendered expressions that are recognised, if they aren't the default
+    /// ones
+    pub markers: Option<Vec<Marker>>,
+    /// How gendered expressions are rendered for a neutral gender, if it isn't the
+    /// default
+    pub neutral_style: Option<NeutralStyle>,
+    /// How the gender of a group is computed, if it isn't the default
+    pub group_rule: Option<GroupRule>,
+}
+
+/// Metadata as it is written in a grammar file, where the agreement of a pronoun set is the
+/// name of a gender, which can be one of the custom gender classes of `genders`
+#[derive(Deserialize, Serialize)]
+#[serde(rename = "Metadata", deny_unknown_fields)]
+struct RawMetadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    language: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    author: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    entry: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pronouns: BTreeMap<String, RawPronounSet>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    genders: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    contractions: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    capitalize_sentences: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    typography: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    markers: Option<Vec<Marker>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    neutral_style: Option<NeutralStyle>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    group_rule: Option<GroupRule>,
+}
+
+/// Pronoun set as it is written in a grammar file
+#[derive(Deserialize, Serialize)]
+#[serde(rename = "PronounSet", deny_unknown_fields)]
+struct RawPronounSet {
+    subject: String,
+    object: String,
+    possessive: String,
+    reflexive: String,
+    agreement: String,
+}
+
+impl TryFrom<RawMetadata> for Metadata {
+    type Error = String;
+
+    fn try_from(raw: RawMetadata) -> Result<Self, String> {
+        let mut pronouns = BTreeMap::new();
+        for (name, p) in raw.pronouns {
+            let agreement = Gender::parse_class(&p.agreement, &raw.genders)
+                .map_err(|e| format!("pronoun set '{}': {}", name, e))?;
+            let set =
+                PronounSet::new(&p.subject, &p.object, &p.possessive, &p.reflexive, agreement);
+            pronouns.insert(name, set);
+        }
+        Ok(Metadata {
+            language: raw.language,
+            author: raw.author,
+            version: raw.version,
+            entry: raw.entry,
+            pronouns,
+            genders: raw.genders,
+            contractions: raw.contractions,
+            capitalize_sentences: raw.capitalize_sentences,
+            typography: raw.typography,
+            markers: raw.markers,
+            neutral_style: raw.neutral_style,
+            group_rule: raw.group_rule,
+        })
+    }
+}
+
+impl From<Metadata> for RawMetadata {
+    fn from(meta: Metadata) -> Self {
+        let genders = &meta.genders;
+        let pronouns = meta
+            .pronouns
+            .into_iter()
+            .map(|(name, p)| {
+                let agreement = p.agreement.marker(genders);
+                let raw = RawPronounSet {
+                    subject: p.subject,
+                    object: p.object,
+                    possessive: p.possessive,
+                    reflexive: p.reflexive,
+                    agreement,
+                };
+                (name, raw)
+            })
+            .collect();
+        RawMetadata {
+            language: meta.language,
+            author: meta.author,
+            version: meta.version,
+            entry: meta.entry,
+            pronouns,
+            genders: meta.genders,
+            contractions: meta.contractions,
+            capitalize_sentences: meta.capitalize_sentences,
+            typography: meta.typography,
+            markers: meta.markers,
+            neutral_style: meta.neutral_style,
+            group_rule: meta.group_rule,
+        }
+    }
+}
+
+impl Metadata {
+    /// Sets the fields that are set in `other`, keeping the others untouched
+    pub(crate) fn merge(&mut self, other: Metadata) {
+        if other.language.is_some() {
+            self.language = other.language;
+        }
+        if other.author.is_some() {
+            self.author = other.author;
+        }
+        if other.version.is_some() {
+            self.version = other.version;
+        }
+        if other.entry.is_some() {
+            self.entry = other.entry;
+        }
+        self.pronouns.extend(other.pronouns);
+        if !other.genders.is_empty() {
+            self.genders = other.genders;
+        }
+        if other.contractions.is_some() {
+            self.contractions = other.contractions;
+        }
+        if other.capitalize_sentences.is_some() {
+            self.capitalize_sentences = other.capitalize_sentences;
+        }
+        if other.typography.is_some() {
+            self.typography = other.typography;
+        }
+        if other.markers.is_some() {
+            self.markers = other.markers;
+        }
+        if other.neutral_style.is_some() {
+            self.neutral_style = other.neutral_style;
+        }
+        if other.group_rule.is_some() {
+            self.group_rule = other.group_rule;
+        }
+    }
+}
+
+/// Syntaxes for gendered expressions that are recognised by default
+pub(crate) const DEFAULT_MARKERS: [Marker; 3] =
+    [Marker::Inclusive, Marker::MedianPoint, Marker::Slash];
+
+/// A syntax for gendered expressions.
+///
+/// `Inclusive`, `MedianPoint` and `Slash` are active by default, which can be changed with
+/// the `"markers"` field of the metadata, or with `Generator::set_markers`. The explicit
+/// syntax must be enabled, as must the others, which conflict with ordinary punctuation:
+/// they are used in French, with a feminine suffix and an optional plural "s".
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Marker {
+    /// `"explicit"`, e.g. "<il|elle|iel>"
+    Explicit,
+    /// `"inclusive"`, for the inclusive markers that the language understands, e.g.
+    /// "amig@s" in Spanish
+    Inclusive,
+    /// `"median_point"`, e.g. "étudiant·e"
+    MedianPoint,
+    /// `"slash"`, e.g. "il/elle/iel"
+    Slash,
+    /// `"period"`, e.g. "étudiant.e.s"
+    Period,
+    /// `"parentheses"`, e.g. "étudiant(e)s"
+    Parentheses,
+    /// `"hyphen"`, e.g. "étudiant-e-s"
+    Hyphen,
+}
+
+/// Definition of a symbol
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Symbol {
+    /// `"symbol": ["alternative", ...]`
+    Compact(Vec<Alternative>),
+    /// `"symbol": {"depends_on": "other", "alternatives": [...]}`
+    Full {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        depends_on: Option<String>,
+        alternatives: Vec<Alternative>,
+    },
+}
+
+/// One of the possible replacements of a symbol
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Alternative {
+    /// `"text"`
+    Compact(String),
+    /// `{"text": "épée", "gender": "f", "weight": 3, "tags": ["sharp"]}`, and `"referent"`
+    /// for the gender of what it refers to, if it differs from `"gender"`
+    Full {
+        text: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        gender: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        referent: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        weight: Option<u32>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        tags: Vec<String>,
+    },
+}
@@ -0,0 +1,1055 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with
+// this file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Language profiles, for the language-specific parts of the generation.
+
+use crate::generator::Gender;
+use crate::numbers;
+use crate::plural::{self, PluralCategory};
+use crate::pronouns::PronounSet;
+
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+
+/// Language-specific behaviour of a generator.
+///
+/// Genere provides implementations for English (`English`), French (`French`), Spanish
+/// (`Spanish`), Portuguese (`Portuguese`) and German (`German`), and a fallback
+/// (`GenericLanguage`) for other languages, which only knows about their plural rules.
+/// You can implement this trait for other languages and register your implementation with
+/// `Generator::register_language` or `Generator::set_language`.
+///
+/// All methods but `code` have a default implementation.
+pub trait Language: Send + Sync {
+    /// Code of the language, e.g. "en" or "fr-CA".
+    fn code(&self) -> &str;
+
+    /// Returns the plural category of an integer.
+    ///
+    /// By default, uses the CLDR rules for the language's code, if genere knows them.
+    fn plural_category(&self, n: i64) -> PluralCategory {
+        plural::category(n, self.code())
+    }
+
+    /// Returns the plural categories that integers can have, in the order in which their
+    /// forms must be given in `singular/plural[#n]` expressions.
+    fn plural_categories(&self) -> &[PluralCategory] {
+        plural::categories(self.code())
+    }
+
+    /// Returns whether a suffix between parentheses after a word, e.g. the "s" of
+    /// "pomme(s)", is a plural one. Others, e.g. the "x" of "f(x)", are left as they are.
+    ///
+    /// By default, only "s" and "es" are plural suffixes.
+    fn is_plural_suffix(&self, _word: &str, suffix: &str) -> bool {
+        ["s", "es"].contains(&suffix)
+    }
+
+    /// Renders a gendered expression when the gender is neutral (or not set), given its
+    /// masculine and feminine forms.
+    ///
+    /// By default, returns `masculine/feminine`.
+    fn neutral(&self, masculine: &str, feminine: &str) -> String {
+        format!("{}/{}", masculine, feminine)
+    }
+
+    /// Splits a word written with an inclusive gender marker, e.g. "amig@s" in Spanish,
+    /// into its masculine and feminine forms. Returns `None` if the word doesn't have such
+    /// a marker, which is always the case by default.
+    fn split_inclusive(&self, _word: &str) -> Option<(String, String)> {
+        None
+    }
+
+    /// Splits a word written in its neutral form, e.g. "amigues" in Spanish, or with an
+    /// inclusive marker that is also a common ending, e.g. "amigx", into its masculine and
+    /// feminine forms. Since such forms can also be ordinary words, this is only used for
+    /// words with their own dependency, e.g. "amigues[hero]". Returns `None` by default.
+    fn split_neutral(&self, _word: &str) -> Option<(String, String)> {
+        None
+    }
+
+    /// Joins the masculine and feminine forms of an expression with the language's word
+    /// for "or", when the neutral style is `NeutralStyle::Doublet`.
+    fn doublet(&self, masculine: &str, feminine: &str) -> String {
+        format!("{} or {}", masculine, feminine)
+    }
+
+    /// Returns the indefinite article to use before a word of a given gender, if the
+    /// language has some. It is used by `{a:symbol}`.
+    fn indefinite_article(&self, _word: &str, _gender: Gender) -> Option<String> {
+        None
+    }
+
+    /// Names of the articles that can be used in `{article:symbol}` expressions, in
+    /// lowercase.
+    ///
+    /// By default, "a" and "an", for the indefinite article.
+    fn articles(&self) -> &[&str] {
+        &["a", "an"]
+    }
+
+    /// Returns an article (one of `articles`) to use before a word of a given gender, e.g.
+    /// "den" for the article "der" with the modifier "akk" in German. It is used by
+    /// `{article.modifiers:symbol}`. Returns `None` if a modifier isn't supported.
+    ///
+    /// By default, returns the indefinite article for "a" and "an", without modifiers.
+    fn article(
+        &self,
+        name: &str,
+        modifiers: &[&str],
+        word: &str,
+        gender: Gender,
+    ) -> Option<String> {
+        match name {
+            "a" | "an" if modifiers.is_empty() => self.indefinite_article(word, gender),
+            _ => None,
+        }
+    }
+
+    /// Applies contractions (e.g. elision) to a generated text, if they are enabled with
+    /// `Generator::set_contractions`.
+    ///
+    /// Escaped characters are still escaped in `s`, e.g. `~<space>` for an escaped space, and
+    /// words starting with an aspirated h are preceded by `~<aspirated>`. Words that can be
+    /// contracted are preceded by `~<contract>`: the forms of gendered expressions, articles
+    /// inserted by `{a:symbol}`, and words that come right before a `{symbol}`. Other words
+    /// are ordinary text and should be left untouched.
+    ///
+    /// By default, the text is returned untouched.
+    fn contract(&self, s: &str) -> String {
+        s.to_string()
+    }
+
+    /// Returns the opening and closing quotation marks that replace straight double quotes
+    /// in `typography`. By default, “ and ”.
+    fn quotes(&self) -> (&str, &str) {
+        ("“", "”")
+    }
+
+    /// Applies typographic conventions to a generated text, if they are enabled with
+    /// `Generator::set_typography`. Unlike `contract`, it is called once escaped characters
+    /// have been restored.
+    ///
+    /// By default, straight double quotes are replaced by `quotes` in turn, single ones by
+    /// curly quotes and apostrophes, runs of spaces are collapsed, and spaces before commas
+    /// and periods are removed.
+    fn typography(&self, s: &str) -> String {
+        default_typography(self, s)
+    }
+
+    /// Converts a text to uppercase.
+    ///
+    /// By default, uses the Unicode case mapping, except for Turkish and Azerbaijani, where
+    /// "i" becomes "İ".
+    fn to_uppercase(&self, s: &str) -> String {
+        match primary_subtag(self.code()) {
+            "tr" | "az" => s.replace('i', "İ").to_uppercase(),
+            _ => s.to_uppercase(),
+        }
+    }
+
+    /// Converts the first letter of a text to uppercase.
+    ///
+    /// By default, the first character is converted along with the combining marks that
+    /// follow it, e.g. a decomposed "é", and the Dutch "ij" digraph is capitalized as a
+    /// single letter ("IJsland"). Other extended grapheme clusters, such as emoji
+    /// sequences, aren't treated as a unit.
+    fn capitalize(&self, s: &str) -> String {
+        lazy_static! {
+            static ref RE_FIRST: Regex = Regex::new(r"^\P{M}\p{M}*").unwrap();
+        }
+
+        if primary_subtag(self.code()) == "nl" {
+            if let Some(rest) = s.strip_prefix("ij") {
+                return format!("IJ{}", rest);
+            }
+        }
+        match RE_FIRST.find(s) {
+            Some(first) => self.to_uppercase(first.as_str()) + &s[first.end()..],
+            None => s.to_string(),
+        }
+    }
+
+    /// Words that aren't capitalized by `title_case`, unless they are the first word, in
+    /// lowercase.
+    fn minor_words(&self) -> &[&str] {
+        &[]
+    }
+
+    /// Converts a text to title case, capitalizing each word but `minor_words`.
+    ///
+    /// Escaped characters are still escaped in `s`, as for `contract`.
+    fn title_case(&self, s: &str) -> String {
+        lazy_static! {
+            static ref RE_WORD: Regex = Regex::new(r"((?:~<\w+>)*)([^\s~]\S*)").unwrap();
+        }
+
+        let mut first = true;
+        RE_WORD
+            .replace_all(s, |caps: &Captures| {
+                let word = &caps[2];
+                let minor = !first && self.minor_words().contains(&word.to_lowercase().as_str());
+                first = false;
+                if minor {
+                    caps[0].to_string()
+                } else {
+                    format!("{}{}", &caps[1], self.capitalize(word))
+                }
+            })
+            .into_owned()
+    }
+
+    /// Spells out a number in words, agreeing with the gender of the counted noun if
+    /// needed. Returns `None` if this isn't supported.
+    fn number_to_words(&self, _n: i64, _gender: Gender) -> Option<String> {
+        None
+    }
+
+    /// Returns the pronouns used by default for a gender, for symbols that don't have a
+    /// pronoun set.
+    ///
+    /// If there are none for the neutral gender, the masculine and feminine forms are
+    /// combined according to the neutral style.
+    fn pronouns(&self, _gender: Gender) -> Option<PronounSet> {
+        None
+    }
+}
+
+/// Default implementation of `Language::typography`
+fn default_typography<L: Language + ?Sized>(language: &L, s: &str) -> String {
+    lazy_static! {
+        static ref RE_OPENING: Regex = Regex::new(r"(^|[\s(\[{])'").unwrap();
+        static ref RE_SPACES: Regex = Regex::new(r"(\S) {2,}").unwrap();
+        static ref RE_PUNCTUATION: Regex = Regex::new(r" +([,.])").unwrap();
+    }
+
+    // Double quotes are opening and closing in turn, single ones are opening after a space
+    let (opening, closing) = language.quotes();
+    let mut quoted = String::with_capacity(s.len());
+    for (i, part) in s.split('"').enumerate() {
+        if i > 0 {
+            quoted.push_str(if i % 2 == 1 { opening } else { closing });
+        }
+        quoted.push_str(part);
+    }
+    let s = RE_OPENING.replace_all(&quoted, "${1}‘").replace('\'', "’");
+    let s = RE_SPACES.replace_all(&s, "${1} ");
+    RE_PUNCTUATION.replace_all(&s, "${1}").into_owned()
+}
+
+/// Returns the primary language subtag of a code, e.g. "tr" for "tr-TR"
+fn primary_subtag(code: &str) -> &str {
+    code.split(['-', '_']).next().unwrap_or(code)
+}
+
+/// Beginnings of words that take "an" though they start with a consonant, or that take
+/// it though they match `A_PREFIXES`
+const AN_PREFIXES: [&str; 9] = [
+    "hour", "honest", "honor", "honour", "heir", "unid", "unim", "unin", "unil",
+];
+
+/// Beginnings of words that take "a" though they start with a vowel
+const A_PREFIXES: [&str; 14] = [
+    "uni", "use", "usu", "uti", "ute", "uto", "ura", "ure", "uri", "eu", "ewe", "one", "once",
+    "ubiq",
+];
+
+/// English
+#[derive(Debug, Clone, Copy, Default)]
+pub struct English;
+
+impl Language for English {
+    fn code(&self) -> &str {
+        "en"
+    }
+
+    fn minor_words(&self) -> &[&str] {
+        &[
+            "a", "an", "the", "and", "but", "or", "nor", "for", "so", "yet", "as", "at", "by",
+            "in", "of", "on", "to", "up", "via",
+        ]
+    }
+
+    /// Returns "a" or "an", according to the pronunciation of the word, which is guessed
+    /// from its spelling, with some exceptions (e.g. "an hour", "a unicorn"), acronyms
+    /// (e.g. "an FBI agent") and numbers (e.g. "an 8", "an 11").
+    fn indefinite_article(&self, word: &str, _gender: Gender) -> Option<String> {
+        let word = word.trim_start_matches(|c: char| !c.is_alphanumeric());
+        let lower = word.to_lowercase();
+        let digits = word.chars().take_while(|c| c.is_ascii_digit()).count();
+        let an = if digits > 0 {
+            // Eight, eleven and eighteen, possibly followed by thousands, millions...
+            word.starts_with('8')
+                || (digits % 3 == 2 && (word.starts_with("11") || word.starts_with("18")))
+        } else if word.len() > 1 && word.chars().all(|c| c.is_ascii_uppercase()) {
+            // Acronyms are read letter by letter
+            word.starts_with(|c| "AEFHILMNORSX".contains(c))
+        } else if AN_PREFIXES.iter().any(|p| lower.starts_with(p)) {
+            true
+        } else if A_PREFIXES.iter().any(|p| lower.starts_with(p)) {
+            false
+        } else {
+            lower.starts_with(|c| "aeiouàâäéèêëîïôöûùü".contains(c))
+        };
+        Some(if an { "an" } else { "a" }.to_string())
+    }
+
+    /// Replaces a marked "a" or "an" with the article required by the following word, so that
+    /// e.g. "the letter a is first" is left untouched
+    fn contract(&self, s: &str) -> String {
+        lazy_static! {
+            static ref RE_ARTICLE: Regex =
+                Regex::new(r"~<contract>([aA]|[aA][nN]) +((?:~<contract>)?)([^\s~]+)").unwrap();
+        }
+
+        RE_ARTICLE
+            .replace_all(s, |caps: &Captures| {
+                let article = self.indefinite_article(&caps[3], Gender::Neutral).unwrap();
+                let article = if caps[1].starts_with('A') {
+                    self.capitalize(&article)
+                } else {
+                    article
+                };
+                format!("{} {}{}", article, &caps[2], &caps[3])
+            })
+            .into_owned()
+    }
+
+    fn number_to_words(&self, n: i64, _gender: Gender) -> Option<String> {
+        Some(numbers::english(n))
+    }
+
+    fn pronouns(&self, gender: Gender) -> Option<PronounSet> {
+        Some(match gender {
+            Gender::Male => PronounSet::new("he", "him", "his", "himself", gender),
+            Gender::Female => PronounSet::new("she", "her", "her", "herself", gender),
+            _ => PronounSet::new("they", "them", "their", "themself", gender),
+        })
+    }
+}
+
+/// French
+#[derive(Debug, Clone, Copy, Default)]
+pub struct French;
+
+impl Language for French {
+    fn code(&self) -> &str {
+        "fr"
+    }
+
+    fn quotes(&self) -> (&str, &str) {
+        ("«\u{202F}", "\u{202F}»")
+    }
+
+    /// Also puts narrow no-break spaces before ";", ":", "!" and "?", and inside « ».
+    fn typography(&self, s: &str) -> String {
+        lazy_static! {
+            static ref RE_BEFORE: Regex =
+                Regex::new(r"([^\s;:!?«(])[ \x{A0}\x{202F}]*([;!?]+|:(?:\s|$))").unwrap();
+            static ref RE_OPENING: Regex = Regex::new(r"«[ \x{A0}\x{202F}]*").unwrap();
+            static ref RE_CLOSING: Regex = Regex::new(r"[ \x{A0}\x{202F}]*»").unwrap();
+        }
+
+        let s = default_typography(self, s);
+        let s = RE_BEFORE.replace_all(&s, "${1}\u{202F}${2}");
+        let s = RE_OPENING.replace_all(&s, "«\u{202F}");
+        RE_CLOSING.replace_all(&s, "\u{202F}»").into_owned()
+    }
+
+    fn minor_words(&self) -> &[&str] {
+        &[
+            "le", "la", "les", "un", "une", "des", "de", "du", "et", "ou", "à", "au", "aux",
+            "en", "sur", "par", "pour", "dans", "avec",
+        ]
+    }
+
+    /// "x" is only a plural suffix after "au", "eu" or "ou", e.g. "cheveu(x)".
+    fn is_plural_suffix(&self, word: &str, suffix: &str) -> bool {
+        match suffix {
+            "s" => true,
+            "x" => ["au", "eu", "ou"].iter().any(|ending| word.ends_with(ending)),
+            _ => false,
+        }
+    }
+
+    fn doublet(&self, masculine: &str, feminine: &str) -> String {
+        format!("{} ou {}", masculine, feminine)
+    }
+
+    /// Elides "le", "la", "de", "que", "je" and so on before a vowel or a mute h (e.g.
+    /// "l'épée"), and contracts "à le", "à les", "de le" and "de les" to "au", "aux", "du"
+    /// and "des".
+    /// Only applies to pairs of words where one of them is marked, and not after a hyphen,
+    /// so that e.g. "décidé de le faire" and "prends-le avec toi" are left untouched.
+    fn contract(&self, s: &str) -> String {
+        lazy_static! {
+            static ref RE_ELISION: Regex = Regex::new(concat!(
+                r"(?i)(^|[^\w-])(~<contract>)?(le|la|de|que|lorsque|puisque|jusque|je|me|te|se|ne)",
+                r" +(~<contract>)?([aeiouâàäéèêëîïôöûùüæœh])"
+            ))
+            .unwrap();
+            static ref RE_SI: Regex =
+                Regex::new(r"(^|[^\w-])(~<contract>)?([sS])i +(~<contract>)?(ils?)\b").unwrap();
+            static ref RE_CONTRACTION: Regex =
+                Regex::new(r"(?i)(^|[^\w-])(~<contract>)?(à|de) +(~<contract>)?(les?)\b").unwrap();
+        }
+
+        let marked = |caps: &Captures| caps.get(2).is_some() || caps.get(4).is_some();
+        let s = RE_ELISION.replace_all(s, |caps: &Captures| {
+            if !marked(caps) {
+                return caps[0].to_string();
+            }
+            let word = &caps[3];
+            format!("{}{}'{}", &caps[1], &word[..word.len() - 1], &caps[5])
+        });
+        let s = RE_SI.replace_all(&s, |caps: &Captures| {
+            if !marked(caps) {
+                return caps[0].to_string();
+            }
+            format!("{}{}'{}", &caps[1], &caps[3], &caps[5])
+        });
+        let s = RE_CONTRACTION.replace_all(&s, |caps: &Captures| {
+            if !marked(caps) {
+                return caps[0].to_string();
+            }
+            let contracted = match (caps[3].to_lowercase().as_str(), caps[5].to_lowercase().as_str()) {
+                ("à", "le") => "au",
+                ("à", _) => "aux",
+                (_, "le") => "du",
+                _ => "des",
+            };
+            if caps[3].starts_with(char::is_uppercase) {
+                format!("{}{}", &caps[1], self.capitalize(contracted))
+            } else {
+                format!("{}{}", &caps[1], contracted)
+            }
+        });
+        s.into_owned()
+    }
+
+    fn indefinite_article(&self, _word: &str, gender: Gender) -> Option<String> {
+        Some(match gender {
+            Gender::Male => "un".to_string(),
+            Gender::Female => "une".to_string(),
+            _ => self.neutral("un", "une"),
+        })
+    }
+
+    fn number_to_words(&self, n: i64, gender: Gender) -> Option<String> {
+        let s = numbers::french(n);
+        // Only a final "un" agrees with the gender of the noun
+        if s == "un" || s.ends_with(" un") || s.ends_with("-un") {
+            let start = &s[..s.len() - 2];
+            Some(match gender {
+                Gender::Male => s.clone(),
+                Gender::Female => format!("{}une", start),
+                _ => format!("{}{}", start, self.neutral("un", "une")),
+            })
+        } else {
+            Some(s)
+        }
+    }
+
+    // The possessive agrees with the possessed noun, not with the owner, so it is given in
+    // its masculine singular form
+    fn pronouns(&self, gender: Gender) -> Option<PronounSet> {
+        match gender {
+            Gender::Male => Some(PronounSet::new("il", "le", "son", "se", gender)),
+            Gender::Female => Some(PronounSet::new("elle", "la", "son", "se", gender)),
+            _ => None,
+        }
+    }
+}
+
+/// Feminine suffixes that `split_french_suffix` recognises
+const FRENCH_SUFFIXES: [&str; 11] =
+    ["e", "ne", "le", "te", "se", "ve", "ive", "rice", "euse", "ère", "esse"];
+
+/// Masculine endings that are replaced by a feminine suffix rather than followed by it,
+/// e.g. "acteur.rice" for "acteur/actrice"
+const FRENCH_ENDINGS: [(&str, &str); 7] = [
+    ("eur", "rice"),
+    ("eur", "euse"),
+    ("eux", "euse"),
+    ("er", "ère"),
+    ("if", "ive"),
+    ("f", "ve"),
+    ("x", "se"),
+];
+
+/// Splits a French word written as a masculine form, a feminine suffix and an optional
+/// plural suffix, e.g. "acteur", "rice" and "s" for "acteur.rice.s", into its masculine
+/// and feminine forms. Returns `None` if the suffix isn't a feminine one.
+///
+/// "le", "ne" and "te" are only feminine suffixes after a doubled consonant, e.g.
+/// "nul.le", "citoyen.ne" or "muet.te", so that e.g. "dis-le" isn't read as one.
+pub(crate) fn split_french_suffix(
+    masculine: &str,
+    suffix: &str,
+    plural: &str,
+) -> Option<(String, String)> {
+    if !FRENCH_SUFFIXES.contains(&suffix) {
+        return None;
+    }
+    if ["le", "ne", "te"].contains(&suffix) && !masculine.ends_with(&suffix[..1]) {
+        return None;
+    }
+    let stem = match FRENCH_ENDINGS
+        .iter()
+        .find(|(ending, feminine)| *feminine == suffix && masculine.ends_with(ending))
+    {
+        Some((ending, _)) => &masculine[..masculine.len() - ending.len()],
+        None => masculine,
+    };
+    let masculine = if masculine.ends_with(['s', 'x']) {
+        masculine.to_string()
+    } else {
+        format!("{}{}", masculine, plural)
+    };
+    Some((masculine, format!("{}{}{}", stem, suffix, plural)))
+}
+
+/// Ending used by `Spanish` and `Portuguese` for the neutral forms of words that end with
+/// "o" in the masculine and "a" in the feminine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InclusiveEnding {
+    /// "e", e.g. "amigues"; articles and pronouns have specific forms, e.g. "les" and
+    /// "elle" in Spanish
+    #[default]
+    E,
+    /// "@", e.g. "amig@s"
+    At,
+    /// "x", e.g. "amigxs"
+    X,
+}
+
+/// Splits a word ending with "@", "xs" (e.g. "amigxs") or "o/a" (e.g. "amigos/as") into its
+/// masculine and feminine forms.
+///
+/// Since many words end with "x" (e.g. "fénix" or "xerox"), a singular "x" is only
+/// understood by `split_neutral_ending`, for words with their own dependency.
+fn split_inclusive_ending(word: &str) -> Option<(String, String)> {
+    lazy_static! {
+        static ref RE_AT: Regex = Regex::new(r"^(\w+)@(s?)$").unwrap();
+        static ref RE_X: Regex = Regex::new(r"^(\w+)x(s)$").unwrap();
+        static ref RE_SLASH: Regex = Regex::new(r"^(\w*)o(s?)/a(s?)$").unwrap();
+    }
+
+    let (stem, plural) = if let Some(caps) = RE_AT.captures(word) {
+        (caps.get(1).unwrap().as_str(), caps.get(2).unwrap().as_str())
+    } else if let Some(caps) = RE_SLASH.captures(word) {
+        if caps[2] != caps[3] {
+            return None;
+        }
+        (caps.get(1).unwrap().as_str(), caps.get(2).unwrap().as_str())
+    } else if let Some(caps) = RE_X.captures(word) {
+        (caps.get(1).unwrap().as_str(), caps.get(2).unwrap().as_str())
+    } else {
+        return None;
+    };
+    Some((format!("{}o{}", stem, plural), format!("{}a{}", stem, plural)))
+}
+
+/// Splits a word written with the "e" ending, e.g. "amigues" or "chique", or with a
+/// singular "x", e.g. "amigx", into its masculine and feminine forms, using `words` (a list
+/// of masculine, feminine and neutral forms) for articles and pronouns.
+fn split_neutral_ending(
+    language: &dyn Language,
+    word: &str,
+    words: &[(&str, &str, &str)],
+) -> Option<(String, String)> {
+    lazy_static! {
+        static ref RE_E: Regex = Regex::new(r"^(\w+?)(qu|gu)?e(s?)$").unwrap();
+        static ref RE_X: Regex = Regex::new(r"^(\w+)x$").unwrap();
+    }
+
+    let lower = word.to_lowercase();
+    let (masculine, feminine) = match words.iter().find(|(_, _, n)| *n == lower) {
+        Some((m, f, _)) => (m.to_string(), f.to_string()),
+        None if RE_X.is_match(&lower) => {
+            let stem = &lower[..lower.len() - 1];
+            (format!("{}o", stem), format!("{}a", stem))
+        }
+        None => {
+            let caps = RE_E.captures(&lower)?;
+            // "qu" and "gu" are spelt "c" and "g" before "o" and "a"
+            let stem = match caps.get(2).map(|m| m.as_str()) {
+                Some("qu") => format!("{}c", &caps[1]),
+                Some(_) => format!("{}g", &caps[1]),
+                None => caps[1].to_string(),
+            };
+            (format!("{}o{}", stem, &caps[3]), format!("{}a{}", stem, &caps[3]))
+        }
+    };
+    Some(if word.starts_with(char::is_uppercase) {
+        (language.capitalize(&masculine), language.capitalize(&feminine))
+    } else {
+        (masculine, feminine)
+    })
+}
+
+/// Renders the neutral form of a word given its masculine and feminine forms, using
+/// `ending` if they end with "o(s)" and "a(s)", and `words` (a list of masculine, feminine
+/// and neutral forms) for the "e" ending.
+fn neutral_ending(
+    language: &dyn Language,
+    masculine: &str,
+    feminine: &str,
+    ending: InclusiveEnding,
+    words: &[(&str, &str, &str)],
+) -> String {
+    lazy_static! {
+        static ref RE_O: Regex = Regex::new(r"^(\w*)o(s?)$").unwrap();
+        static ref RE_A: Regex = Regex::new(r"^(\w*)a(s?)$").unwrap();
+    }
+
+    if ending == InclusiveEnding::E {
+        let (m, f) = (masculine.to_lowercase(), feminine.to_lowercase());
+        if let Some((_, _, n)) = words.iter().find(|(wm, wf, _)| *wm == m && *wf == f) {
+            return if masculine.starts_with(char::is_uppercase) {
+                language.capitalize(n)
+            } else {
+                n.to_string()
+            };
+        }
+    }
+    if let (Some(m), Some(f)) = (RE_O.captures(masculine), RE_A.captures(feminine)) {
+        if m[1] == f[1] && m[2] == f[2] {
+            let stem = &m[1];
+            return match ending {
+                // "c" and "g" are spelt "qu" and "gu" before "e"
+                InclusiveEnding::E if stem.ends_with('c') => {
+                    format!("{}que{}", &stem[..stem.len() - 1], &m[2])
+                }
+                InclusiveEnding::E if stem.ends_with('g') => format!("{}ue{}", stem, &m[2]),
+                InclusiveEnding::E => format!("{}e{}", stem, &m[2]),
+                InclusiveEnding::At => format!("{}@{}", stem, &m[2]),
+                InclusiveEnding::X => format!("{}x{}", stem, &m[2]),
+            };
+        }
+    }
+    format!("{}/{}", masculine, feminine)
+}
+
+/// Masculine, feminine and neutral forms of Spanish articles and pronouns
+const SPANISH_WORDS: [(&str, &str, &str); 7] = [
+    ("el", "la", "le"),
+    ("los", "las", "les"),
+    ("él", "ella", "elle"),
+    ("ellos", "ellas", "elles"),
+    ("un", "una", "une"),
+    ("unos", "unas", "unes"),
+    ("aquel", "aquella", "aquelle"),
+];
+
+/// Masculine, feminine and neutral forms of Portuguese articles and pronouns
+const PORTUGUESE_WORDS: [(&str, &str, &str); 7] = [
+    ("o", "a", "ê"),
+    ("os", "as", "ês"),
+    ("ele", "ela", "elu"),
+    ("eles", "elas", "elus"),
+    ("dele", "dela", "delu"),
+    ("um", "uma", "ume"),
+    ("uns", "umas", "umes"),
+];
+
+/// Spanish.
+///
+/// Words written with an inclusive marker, e.g. "amig@s", "amigxs" or "amigos/as", are
+/// understood as gendered expressions, and the neutral gender is rendered with the
+/// "e" ending by default ("amigues", "les", "elle").
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Spanish {
+    ending: InclusiveEnding,
+}
+
+impl Spanish {
+    /// Creates a language profile that uses the given ending for the neutral gender.
+    pub fn with_ending(ending: InclusiveEnding) -> Self {
+        Spanish { ending }
+    }
+}
+
+impl Language for Spanish {
+    fn code(&self) -> &str {
+        "es"
+    }
+
+    fn quotes(&self) -> (&str, &str) {
+        ("«", "»")
+    }
+
+    fn minor_words(&self) -> &[&str] {
+        &[
+            "el", "la", "los", "las", "un", "una", "y", "e", "o", "u", "de", "del", "a", "al",
+            "en", "con", "por", "para",
+        ]
+    }
+
+    fn split_inclusive(&self, word: &str) -> Option<(String, String)> {
+        split_inclusive_ending(word)
+    }
+
+    fn split_neutral(&self, word: &str) -> Option<(String, String)> {
+        split_neutral_ending(self, word, &SPANISH_WORDS)
+    }
+
+    fn neutral(&self, masculine: &str, feminine: &str) -> String {
+        neutral_ending(self, masculine, feminine, self.ending, &SPANISH_WORDS)
+    }
+
+    fn doublet(&self, masculine: &str, feminine: &str) -> String {
+        format!("{} o {}", masculine, feminine)
+    }
+
+    fn indefinite_article(&self, _word: &str, gender: Gender) -> Option<String> {
+        Some(match gender {
+            Gender::Male => "un".to_string(),
+            Gender::Female => "una".to_string(),
+            _ => self.neutral("un", "una"),
+        })
+    }
+}
+
+/// Portuguese.
+///
+/// Words written with an inclusive marker, e.g. "amig@s", "amigxs" or "amigos/as", are
+/// understood as gendered expressions, and the neutral gender is rendered with the
+/// "e" ending by default ("amigues", "ês", "elu").
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Portuguese {
+    ending: InclusiveEnding,
+}
+
+impl Portuguese {
+    /// Creates a language profile that uses the given ending for the neutral gender.
+    pub fn with_ending(ending: InclusiveEnding) -> Self {
+        Portuguese { ending }
+    }
+}
+
+impl Language for Portuguese {
+    fn code(&self) -> &str {
+        "pt"
+    }
+
+    fn minor_words(&self) -> &[&str] {
+        &[
+            "o", "a", "os", "as", "um", "uma", "e", "ou", "de", "do", "da", "dos", "das", "em",
+            "no", "na", "com", "por", "para",
+        ]
+    }
+
+    fn split_inclusive(&self, word: &str) -> Option<(String, String)> {
+        split_inclusive_ending(word)
+    }
+
+    fn split_neutral(&self, word: &str) -> Option<(String, String)> {
+        split_neutral_ending(self, word, &PORTUGUESE_WORDS)
+    }
+
+    fn neutral(&self, masculine: &str, feminine: &str) -> String {
+        neutral_ending(self, masculine, feminine, self.ending, &PORTUGUESE_WORDS)
+    }
+
+    fn doublet(&self, masculine: &str, feminine: &str) -> String {
+        format!("{} ou {}", masculine, feminine)
+    }
+
+    fn indefinite_article(&self, _word: &str, gender: Gender) -> Option<String> {
+        Some(match gender {
+            Gender::Male => "um".to_string(),
+            Gender::Female => "uma".to_string(),
+            _ => self.neutral("um", "uma"),
+        })
+    }
+}
+
+/// Marker used by `German` for the neutral forms of words whose feminine form ends with
+/// "in" or "innen".
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GermanMarker {
+    /// Gendersternchen, e.g. "Lehrer*innen"
+    #[default]
+    Star,
+    /// Colon, e.g. "Lehrer:innen"
+    Colon,
+    /// Gender gap, e.g. "Lehrer_innen"
+    Gap,
+    /// Binnen-I, e.g. "LehrerInnen"
+    BinnenI,
+}
+
+/// Declension of the definite article, by case (nominative, accusative, dative, genitive)
+/// and by masculine, feminine, neuter and plural forms
+const DER: [[&str; 4]; 4] = [
+    ["der", "die", "das", "die"],
+    ["den", "die", "das", "die"],
+    ["dem", "der", "dem", "den"],
+    ["des", "der", "des", "der"],
+];
+
+/// Declension of the indefinite article, which has no plural forms
+const EIN: [[&str; 3]; 4] = [
+    ["ein", "eine", "ein"],
+    ["einen", "eine", "ein"],
+    ["einem", "einer", "einem"],
+    ["eines", "einer", "eines"],
+];
+
+/// German.
+///
+/// Words written with a gender marker, e.g. "Lehrer*innen", "Lehrer:innen", "Lehrer_innen"
+/// or "LehrerInnen", are understood as gendered expressions, and the neutral gender is
+/// rendered with the Gendersternchen by default ("Lehrer*innen", "der*die").
+///
+/// The definite and indefinite articles are declined with `{der.akk:symbol}` or
+/// `{ein.dat:symbol}`, and `pl` for the plural. Since the neutral gender means that the
+/// gender isn't known, neuter nouns must use the third gender class, e.g. with
+/// `"genders": ["m", "f", "n"]` in the grammar's metadata.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct German {
+    marker: GermanMarker,
+}
+
+impl German {
+    /// Creates a language profile that uses the given marker for the neutral gender.
+    pub fn with_marker(marker: GermanMarker) -> Self {
+        German { marker }
+    }
+}
+
+impl Language for German {
+    fn code(&self) -> &str {
+        "de"
+    }
+
+    fn quotes(&self) -> (&str, &str) {
+        ("„", "“")
+    }
+
+    fn minor_words(&self) -> &[&str] {
+        &[
+            "der", "die", "das", "den", "dem", "des", "ein", "eine", "und", "oder", "von",
+            "zu", "mit", "in", "im", "am", "an", "auf", "für",
+        ]
+    }
+
+    fn is_plural_suffix(&self, _word: &str, suffix: &str) -> bool {
+        ["e", "n", "en", "er", "nen", "s"].contains(&suffix)
+    }
+
+    fn split_inclusive(&self, word: &str) -> Option<(String, String)> {
+        lazy_static! {
+            static ref RE_MARKER: Regex = Regex::new(r"^(\w+)[*:_](in(?:nen)?)$").unwrap();
+            static ref RE_BINNEN_I: Regex = Regex::new(r"^(\w*\p{Ll})I(n(?:nen)?)$").unwrap();
+        }
+
+        let (stem, suffix) = if let Some(caps) = RE_MARKER.captures(word) {
+            (caps[1].to_string(), caps[2].to_string())
+        } else if let Some(caps) = RE_BINNEN_I.captures(word) {
+            (caps[1].to_string(), format!("i{}", &caps[2]))
+        } else {
+            return None;
+        };
+        Some((stem.clone(), stem + &suffix))
+    }
+
+    fn neutral(&self, masculine: &str, feminine: &str) -> String {
+        let separator = match self.marker {
+            GermanMarker::Star => "*",
+            GermanMarker::Colon => ":",
+            GermanMarker::Gap => "_",
+            GermanMarker::BinnenI => "/",
+        };
+        match feminine.strip_prefix(masculine) {
+            Some(suffix @ "in") | Some(suffix @ "innen") => match self.marker {
+                GermanMarker::BinnenI => format!("{}I{}", masculine, &suffix[1..]),
+                _ => format!("{}{}{}", masculine, separator, suffix),
+            },
+            _ => format!("{}{}{}", masculine, separator, feminine),
+        }
+    }
+
+    fn doublet(&self, masculine: &str, feminine: &str) -> String {
+        format!("{} oder {}", masculine, feminine)
+    }
+
+    fn indefinite_article(&self, word: &str, gender: Gender) -> Option<String> {
+        self.article("ein", &[], word, gender)
+    }
+
+    fn articles(&self) -> &[&str] {
+        &["a", "an", "der", "ein"]
+    }
+
+    fn article(
+        &self,
+        name: &str,
+        modifiers: &[&str],
+        _word: &str,
+        gender: Gender,
+    ) -> Option<String> {
+        let mut case = 0;
+        let mut plural = false;
+        for modifier in modifiers {
+            match *modifier {
+                "nom" => case = 0,
+                "akk" => case = 1,
+                "dat" => case = 2,
+                "gen" => case = 3,
+                "pl" => plural = true,
+                _ => return None,
+            }
+        }
+        let form = match gender {
+            _ if plural => 3,
+            Gender::Male | Gender::Class(0) => 0,
+            Gender::Female | Gender::Class(1) => 1,
+            Gender::Class(2) => 2,
+            _ => return None,
+        };
+        match name {
+            "der" => Some(DER[case][form].to_string()),
+            "a" | "an" | "ein" => EIN[case].get(form).map(|s| s.to_string()),
+            _ => None,
+        }
+    }
+
+    fn pronouns(&self, gender: Gender) -> Option<PronounSet> {
+        match gender {
+            Gender::Male | Gender::Class(0) => {
+                Some(PronounSet::new("er", "ihn", "sein", "sich", gender))
+            }
+            Gender::Female | Gender::Class(1) => {
+                Some(PronounSet::new("sie", "sie", "ihr", "sich", gender))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Fallback for languages without a specific implementation, which only knows about the
+/// language's plural rules.
+#[derive(Debug, Clone)]
+pub struct GenericLanguage {
+    code: String,
+}
+
+impl GenericLanguage {
+    /// Creates a language profile for the given code.
+    pub fn new(code: &str) -> Self {
+        GenericLanguage {
+            code: code.to_string(),
+        }
+    }
+}
+
+impl Language for GenericLanguage {
+    fn code(&self) -> &str {
+        &self.code
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////
+//                                    TESTS
+///////////////////////////////////////////////////////////////////////////////////////////
+
+#[test]
+fn french() {
+    assert_eq!(French.number_to_words(21, Gender::Female).unwrap(), "vingt et une");
+    assert_eq!(French.number_to_words(81, Gender::Neutral).unwrap(), "quatre-vingt-un/une");
+    assert_eq!(French.number_to_words(22, Gender::Female).unwrap(), "vingt-deux");
+    assert_eq!(French.number_to_words(1_000_000, Gender::Female).unwrap(), "un million");
+    assert_eq!(French.plural_category(0), PluralCategory::One);
+    assert_eq!(French.capitalize("élan"), "Élan");
+    assert_eq!(French.capitalize("e\u{301}lan"), "E\u{301}lan");
+    assert_eq!(French.title_case("le nom de la ~<aspirated>hache"), "Le Nom de la ~<aspirated>Hache");
+    assert_eq!(French.contract("~<contract>le épée de ~<contract>le arbre, à ~<contract>les enfants"),
+               "l'épée de l'arbre, aux enfants");
+    assert_eq!(French.contract("De ~<contract>le pain, à ~<contract>le homme, que ~<contract>il, si ~<contract>il, si ~<contract>elle"),
+               "Du pain, à l'homme, qu'il, s'il, si ~<contract>elle");
+    assert_eq!(French.contract("~<contract>le ~<aspirated>héros, de~<space>~<contract>le faire"),
+               "~<contract>le ~<aspirated>héros, de~<space>~<contract>le faire");
+    assert_eq!(French.contract("Il a décidé de le faire, et commence à les voir"),
+               "Il a décidé de le faire, et commence à les voir");
+    assert_eq!(French.contract("Prends-~<contract>le avec toi, de le ami"),
+               "Prends-~<contract>le avec toi, de le ami");
+}
+
+#[test]
+fn spanish_and_portuguese() {
+    let split = |word| split_inclusive_ending(word);
+    assert_eq!(split("amig@s"), Some(("amigos".into(), "amigas".into())));
+    assert_eq!(split("Todxs"), Some(("Todos".into(), "Todas".into())));
+    assert_eq!(split("amigx"), None);
+    assert_eq!(split("amigo/a"), Some(("amigo".into(), "amiga".into())));
+    assert_eq!(split("amigos/a"), None);
+    for word in &["tórax", "Max", "ex", "lux", "xerox", "durex", "fénix", "box"] {
+        assert_eq!(split(word), None);
+    }
+    assert_eq!(split("amigo"), None);
+    let es = Spanish::default();
+    assert_eq!(es.neutral("amigos", "amigas"), "amigues");
+    assert_eq!(es.neutral("chico", "chica"), "chique");
+    assert_eq!(es.neutral("Los", "Las"), "Les");
+    assert_eq!(es.neutral("él", "ella"), "elle");
+    assert_eq!(es.neutral("actor", "actriz"), "actor/actriz");
+    assert_eq!(Spanish::with_ending(InclusiveEnding::At).neutral("los", "las"), "l@s");
+    assert_eq!(Spanish::with_ending(InclusiveEnding::X).neutral("el", "la"), "el/la");
+    assert_eq!(Portuguese::default().neutral("o", "a"), "ê");
+    assert_eq!(Portuguese::default().neutral("amigos", "amigas"), "amigues");
+    assert_eq!(Portuguese::default().indefinite_article("", Gender::Neutral).unwrap(), "ume");
+    assert_eq!(es.split_neutral("amigues"), Some(("amigos".into(), "amigas".into())));
+    assert_eq!(es.split_neutral("Chique"), Some(("Chico".into(), "Chica".into())));
+    assert_eq!(es.split_neutral("les"), Some(("los".into(), "las".into())));
+    assert_eq!(es.split_neutral("amigo"), None);
+    assert_eq!(es.split_neutral("chicx"), Some(("chico".into(), "chica".into())));
+    assert_eq!(Portuguese::default().split_neutral("elu"), Some(("ele".into(), "ela".into())));
+}
+
+#[test]
+fn casing() {
+    assert_eq!(GenericLanguage::new("tr").capitalize("istanbul"), "İstanbul");
+    assert_eq!(GenericLanguage::new("az-AZ").to_uppercase("iki ılık"), "İKİ ILIK");
+    assert_eq!(GenericLanguage::new("nl").capitalize("ijsland"), "IJsland");
+    assert_eq!(GenericLanguage::new("it").capitalize("istanbul"), "Istanbul");
+    assert_eq!(English.title_case("the lord of the rings"), "The Lord of the Rings");
+    assert_eq!(English.title_case("  a  tale~<space>of two cities"), "  A  Tale~<space>of Two Cities");
+    assert_eq!(English.capitalize(""), "");
+}
+
+#[test]
+fn typography() {
+    assert_eq!(English.typography(r#"He said "it's  fine" , 'ok' ."#),
+               "He said “it’s fine”, ‘ok’.");
+    assert_eq!(French.typography(r#"Il dit : "l'épée ?! Ah!" Voir http://a.fr à 12:30; « oh»"#),
+               "Il dit\u{202F}: «\u{202F}l’épée\u{202F}?! Ah\u{202F}!\u{202F}» Voir http://a.fr à 12:30\u{202F}; «\u{202F}oh\u{202F}»");
+    assert_eq!(German::default().typography(r#"Er sagt "ja""#), "Er sagt „ja“");
+    assert_eq!(GenericLanguage::new("it").typography("a  b"), "a b");
+}
+
+#[test]
+fn english_and_generic() {
+    assert_eq!(English.number_to_words(21, Gender::Female).unwrap(), "twenty-one");
+    let article = |word| English.indefinite_article(word, Gender::Neutral).unwrap();
+    assert_eq!(article("apple"), "an");
+    assert_eq!(article("banana"), "a");
+    assert_eq!(article("hour"), "an");
+    assert_eq!(article("unicorn"), "a");
+    assert_eq!(article("uninvited"), "an");
+    assert_eq!(article("FBI"), "an");
+    assert_eq!(article("UFO"), "a");
+    assert_eq!(article("11,000"), "an");
+    assert_eq!(article("110"), "a");
+    assert_eq!(article("\"elephant\""), "an");
+    assert_eq!(English.contract("~<contract>A apple, ~<contract>an banana and ~<contract>a 8"),
+               "An apple, a banana and an 8");
+    assert_eq!(English.contract("The letter a is first."), "The letter a is first.");
+    assert_eq!(English.plural_category(0), PluralCategory::Other);
+    let ru = GenericLanguage::new("ru-RU");
+    assert_eq!(ru.plural_category(3), PluralCategory::Few);
+    assert!(ru.number_to_words(3, Gender::Neutral).is_none());
+    assert_eq!(ru.neutral("он", "она"), "он/она");
+}
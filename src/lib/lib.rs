@@ -33,9 +33,13 @@
 //! It is possible to use Genere as a binary:
 //!
 //! ```bash
+//! $ genere main file.json
 //! $ genere main < file.json
 //! ```
-//! will instantiate the `main` symbol in the `file.json` file.
+//! will instantiate the `main` symbol in the `file.json` file (which is read from standard
+//! input if no file is given). If no symbol is given, the `entry` symbol of the grammar's
+//! metadata is instantiated (see below). `genere --from-tracery file.json` translates a
+//! Tracery grammar into genere's syntax, and `genere --help` lists all the options.
 //!
 //! Genere is, however, primarily a [Rust](https://rust-lang.org) library, so it can be used in programs written in Rust: you only have to add
 //!
@@ -45,6 +49,20 @@
 //!
 //! In the `dependencies` section of your `Cargo.toml` file.
 //!
+//! ## Grammar formats
+//!
+//! Grammars are written in JSON by default, but YAML, TOML and RON are also supported if
+//! the `yaml`, `toml` or `ron` features are enabled, respectively (see the `add_yaml`,
+//! `add_toml` and `add_ron` methods). The structure of the grammar is the same in all cases.
+//!
+//! The binary guesses the format from the extension of the file, or it can be set with the
+//! `--format` option:
+//!
+//! ```bash
+//! $ genere main file.yaml
+//! $ genere --format toml main < file.toml
+//! ```
+//!
 //! ## Text generation
 //!
 //! Genere is inspired by [Tracery](http://tracery.io/) and thus has a similar syntax to allow
@@ -59,7 +77,7 @@
 //!     "class": ["mage", "warrior", "thief", "rogue", "barbarian"],
 //!     "race": ["human", "dwarvish", "elvish", "vampire"],
 //!     "text": ["{name} {last_name} is a {race} {class}.",
-//! 	     "Meet {name} {last_name}, A proud {class}!"]
+//!          "Meet {name} {last_name}, A proud {class}!"]
 //! }
 //! "#;
 //!
@@ -121,6 +139,37 @@
 //!
 //! will display "This is a good dog", "This is A GOOD DOG" and "A good dog" for "text1", "text2" and "text3" respectively.
 //!
+//! Capitalization follows the grammar's language, e.g. "istanbul" becomes "İstanbul" in
+//! Turkish and "ijsland" becomes "IJsland" in Dutch, and a first letter written with
+//! combining accents keeps them. The `.title` modifier converts a replacement to title case,
+//! leaving minor words such as "of" or "the" in lowercase, e.g. `{book.title}`.
+//!
+//! ```
+//! # use genere::Generator;
+//! let mut gen = Generator::new();
+//! gen.add("book", &["the lord of the rings"]).unwrap();
+//! assert_eq!(&gen.msg("{book.title}", &[]).unwrap(), "The Lord of the Rings");
+//! ```
+//!
+//! The first letter of each sentence can also be capitalized automatically, with
+//! `"capitalize_sentences": true` in the metadata or `Generator::set_capitalize_sentences`.
+//! This happens after gender adaptation, and leaves words such as "iPhone" untouched. A
+//! period that doesn't end a sentence can be escaped, e.g. "etc~.", and "~=" prevents the
+//! capitalization of the next word.
+//!
+//! ```
+//! # use genere::Generator;
+//! let json = r#"
+//! {
+//!     "$meta": {"capitalize_sentences": true},
+//!     "hero": ["the knight[m]"],
+//!     "text[hero]": ["{hero} arrives, i~.e~. the hero. he/she smiles."]
+//! }"#;
+//! let mut gen = Generator::new();
+//! gen.add_json(json).unwrap();
+//! assert_eq!(&gen.instantiate("text").unwrap(), "The knight arrives, i.e. the hero. He smiles.");
+//! ```
+//!
 //! ## Gender adaptation
 //!
 //! Genere seeks to allow easy generation of sentences that are grammaticaly gender accurate:
@@ -149,6 +198,11 @@
 //! It is also possible to specify a neutral gender, by using `[n]` in the definition and by
 //! adding a `/` in the replacement string (e.g. `He/She/They`). If it isn't specified in the
 //! replacement string, both male and female version will be outputted (e.g. `He/She` instead of `Them`).
+//! This can be changed with `Generator::set_neutral_style`, e.g. to output both forms
+//! joined with "or" (`NeutralStyle::Doublet`), a median point ("sorcier·ère",
+//! `NeutralStyle::MedianPoint`), the feminine ending between parentheses ("sorcier(ère)",
+//! `NeutralStyle::Parenthetical`), only the masculine form (`NeutralStyle::Masculine`) or to
+//! fail with an error (`NeutralStyle::Error`).
 //!
 //! Sometimes a sentence might use various gendered elements and not just depend on only one symbol's gender.
 //! For each gender variation, it is possible to specify a "dependency":
@@ -166,14 +220,459 @@
 //! after, not allowing to have spaces in your expressions. If you want to insert a space in a
 //! gender adaptation expression, you must escape it with `~`, e.g.: "du/de~ la"
 //!
+//! ## Object syntax
+//!
+//! Instead of a list of strings, a symbol can also be defined with an object, and each
+//! alternative can also be an object. This is mostly useful when the grammar is generated
+//! by another program, as it avoids having to insert markers in the strings:
+//!
+//! ```json
+//! {
+//!     "weapon": [{"text": "épée", "gender": "f", "weight": 3, "tags": ["sharp"]},
+//!                {"text": "gourdin", "gender": "m"}],
+//!     "text": {"depends_on": "weapon",
+//!              "alternatives": ["Un·e {weapon}"]}
+//! }
+//! ```
+//!
+//! The `weight` of an alternative (1 by default) makes it more or less likely to be picked.
+//! The `tags` of an alternative allow to only pick among the alternatives that have all the
+//! given tags, using the `{symbol#tag}` syntax (e.g. `{weapon#sharp}`). Note that, as with
+//! `{symbol}`, the value is then fixed, so tags are only taken into account the first time
+//! the symbol is instantiated.
+//!
+//! ## Comments and metadata
+//!
+//! Keys starting with `$` are reserved. `"$comment"` can be used to add comments to a
+//! grammar (either at the top level, or inside the objects describing a symbol or an
+//! alternative), while `"$meta"` sets metadata about the grammar:
+//!
+//! ```
+//! # use genere::Generator;
+//! let json = r#"
+//! {
+//!     "$comment": "A very simple grammar",
+//!     "$meta": {"language": "en", "author": "Me", "version": "1.0", "entry": "main"},
+//!     "main": ["Hello, world!"]
+//! }"#;
+//! let mut gen = Generator::new();
+//! gen.add_json(json).unwrap();
+//! assert_eq!(gen.metadata().entry.as_deref(), Some("main"));
+//! ```
+//!
+//! When no symbol is given to the binary, it instantiates the `entry` symbol.
+//!
+//! ## Exporting grammars
+//!
+//! The symbols of a `Generator` (and its metadata) can be exported with `to_json` (or
+//! `to_yaml`, `to_toml` and `to_ron` if the corresponding features are enabled), e.g. to save
+//! a grammar that was built with `add`. Gender dependencies are exported in the symbol name
+//! (`symbol[dependency]`), and escaped characters are escaped again, so loading the result
+//! gives back the same grammar. Comments are not exported.
+//!
+//! ## Tracery grammars
+//!
+//! [Tracery](http://tracery.io/) grammars can be loaded with `add_tracery_json`, or
+//! translated into genere syntax with `tracery_to_json` (or `genere --from-tracery` with the
+//! binary). Since Tracery picks a new value each time a symbol is referenced, `#symbol#`
+//! is translated to `{{symbol}}`, `#symbol.capitalize#` to `{{Symbol}}` and `#symbol.a#` to
+//! `{{a:symbol}}`. Characters with a special meaning in genere are escaped. Elements that
+//! can't be translated are all reported in the returned error: this includes the modifiers
+//! other than `.capitalize` and `.a`, such as `.s`, since genere has no way to pluralize
+//! an arbitrary word.
+//! Actions are translated too: `[hero:#name#]` sets `hero` (see below), and `[hero:POP]`
+//! unsets it.
+//!
+//! ## Setting values
+//!
+//! A replacement can set the value of a symbol for the rest of the generation, with
+//! `{set symbol = {other}}` (which instantiates `other`, and also copies its gender) or
+//! `{set symbol = "some text"}` (which can contain references and a gender marker). The
+//! value is forgotten with `{unset symbol}`:
+//!
+//! ```
+//! # use genere::Generator;
+//! let json = r#"
+//! {
+//!     "name": ["Marie[f]"],
+//!     "text": ["{set hero = {name}}{hero}: il/elle[hero]. {set hero = \"Jean[m]\"}{hero}: il/elle[hero]."]
+//! }"#;
+//! let mut gen = Generator::new();
+//! gen.add_json(json).unwrap();
+//! assert_eq!(&gen.instantiate("text").unwrap(), "Marie: elle. Jean: il.");
+//! ```
+//!
+//! Unlike other values, values that were set explicitly are kept by `{{symbol}}`, so they
+//! are also visible in the new replacements it generates.
+//!
+//! ## Symbols with parameters
+//!
+//! A symbol can take parameters, declared between parentheses in its name. It is then
+//! called with the symbols to use as arguments, e.g. `{greet(villain)}`:
+//!
+//! ```
+//! # use genere::Generator;
+//! let json = r#"
+//! {
+//!     "hero": ["John[m]"],
+//!     "villain": ["Joan[f]"],
+//!     "greet(who)[who]": ["Hello {who}, nice to see him/her!"],
+//!     "text": ["{greet(hero)} {greet(villain)}"]
+//! }"#;
+//! let mut gen = Generator::new();
+//! gen.add_json(json).unwrap();
+//! assert_eq!(&gen.instantiate("text").unwrap(),
+//!            "Hello John, nice to see him! Hello Joan, nice to see her!");
+//! ```
+//!
+//! While the symbol is instantiated, each parameter takes the value (and gender) of the
+//! corresponding argument; it can thus also be used as a gender dependency. Unlike other
+//! symbols, the result of a call isn't kept, so each call is instantiated again, and neither
+//! are the symbols first instantiated during the call, since they may use the parameters.
+//! Calling a symbol with the wrong number of arguments is an error.
+//!
+//! ## Numbers
+//!
+//! Besides symbols, braces can contain numeric expressions: integers, dice (`{2d6}`, or
+//! `{d20}` for a single die), ranges (`{1-6}`, without spaces, picks a number between 1 and
+//! 6), symbols whose value is a number, the `+`, `-`, `*` and `/` operators and parentheses.
+//! Values can also be set to the result of an expression:
+//!
+//! ```
+//! # use genere::Generator;
+//! let json = r#"
+//! {
+//!     "text": ["{set gold = 2d20}You find {gold} gold coins, worth {gold * 10} silver coins."]
+//! }"#;
+//! let mut gen = Generator::new();
+//! gen.add_json(json).unwrap();
+//! let s = gen.instantiate_from_seed("text", 42).unwrap();
+//! # assert_eq!(s, gen.instantiate_from_seed("text", 42).unwrap());
+//! ```
+//!
+//! Random numbers use the same random number generator as the rest of the generation, so
+//! the result is deterministic with `instantiate_from_seed`. Note that `{1 - 6}` (with
+//! spaces) is a subtraction, and that symbol names that start with a digit can't be
+//! referenced.
+//!
+//! ### Numbers in words
+//!
+//! The `.words` modifier spells out a number, e.g. `{gold.words}` or `{(gold * 2).words}`,
+//! in the language set in the grammar's metadata (English by default; French is also
+//! supported). In French, "un" agrees with the gender of the counted noun, using the
+//! symbol's gender dependency or the one given after the modifier:
+//!
+//! ```
+//! # use genere::Generator;
+//! let json = r#"
+//! {
+//!     "$meta": {"language": "fr"},
+//!     "n": ["21"],
+//!     "arme": ["épée[f]"],
+//!     "text": ["Il/Elle[arme] coûte {n.words[arme]} pièces."]
+//! }"#;
+//! let mut gen = Generator::new();
+//! gen.add_json(json).unwrap();
+//! assert_eq!(&gen.instantiate("text").unwrap(), "Elle coûte vingt et une pièces.");
+//! ```
+//!
 //! ## Additional gender syntax
 //!
 //! It is also possible to use the "median point" syntax used e.g. in french: "C'est un·e sorci·er·ère." is equivalent to "C'est un/une sorcier/sorcière".
 //!
+//! Alternatively, an explicit syntax lists the forms between angle brackets, separated by
+//! '|': `<il est|elle est|iel est>[hero]` is equivalent to `il/elle/iel[hero] est`. Forms
+//! can contain spaces, and they aren't adapted again.
+//!
+//! Since a word with a slash or a median point isn't always a gendered expression (e.g. in
+//! dates, URLs or the Catalan "col·lecció"), the recognised syntaxes can be chosen with the
+//! `"markers"` field of the metadata or `Generator::set_markers`. By default, they are
+//! `"inclusive"` (see below), `"median_point"` and `"slash"`; the explicit syntax must be
+//! enabled with `"explicit"`. In strict mode, with only `"explicit"`, gender alternation
+//! only happens in explicit expressions.
+//!
+//! Other French notations can also be enabled, although they conflict with ordinary
+//! punctuation: `"period"` for "étudiant.e.s", `"parentheses"` for "étudiant(e)s" and
+//! `"hyphen"` for "étudiant-e-s". They are made of the masculine form, a feminine suffix
+//! such as "e", "ne", "rice" or "euse", and an optional plural "s". "le", "ne" and "te"
+//! must follow a doubled consonant, as in "citoyen-ne", so that "dis-le" is left untouched.
+//!
+//! ```
+//! # use genere::Generator;
+//! let json = r#"
+//! {
+//!     "$meta": {"language": "fr", "markers": ["period", "parentheses"]},
+//!     "hero": ["Léa[f]"],
+//!     "text[hero]": ["{hero} et ses ami(e)s sont les meilleur.e.s acteur.rice.s."]
+//! }"#;
+//! let mut gen = Generator::new();
+//! gen.add_json(json).unwrap();
+//! assert_eq!(&gen.instantiate("text").unwrap(),
+//!            "Léa et ses amies sont les meilleures actrices.");
+//! ```
+//!
+//! ## Plurals
+//!
+//! Besides a symbol for the gender, dependencies can contain a symbol whose value is a number,
+//! prefixed with `#`, e.g. `[#n]` or `[hero,#n]`, either for a whole symbol or for a single
+//! expression. Plural forms are then picked according to the [CLDR plural
+//! rules](https://cldr.unicode.org/index/cldr-spec/plural-rules) of the grammar's language:
+//!
+//! * `apple/apples[#n]` picks a form according to the number, if it doesn't also depend on a
+//!   gender (for languages with more forms, they are given in the CLDR order, e.g.
+//!   one/few/many for Russian);
+//! * a suffix between parentheses, e.g. `pomme(s)`, `il/elle(s)` or `sorci·er·ère(s)`, is
+//!   added if the number isn't singular. It is left untouched if there is no number, or if
+//!   the suffix isn't a plural one for the language, e.g. `f(x)`.
+//!
+//! ```
+//! # use genere::Generator;
+//! let json = r#"
+//! {
+//!     "$meta": {"language": "fr"},
+//!     "n": ["3"],
+//!     "hero": ["Marie[f]"],
+//!     "text[hero,#n]": ["{n} sorci·er·ère(s). Il/Elle(s) a/ont[#n] un chat."]
+//! }"#;
+//! let mut gen = Generator::new();
+//! gen.add_json(json).unwrap();
+//! assert_eq!(&gen.instantiate("text").unwrap(), "3 sorcières. Elles ont un chat.");
+//! ```
+//!
+//! ## Groups
+//!
+//! Dependencies can contain several symbols, e.g. `[hero,sidekick]`, for expressions that
+//! refer to a group. Gendered expressions then agree with the gender of the group, which is
+//! by default feminine if all its members are, masculine otherwise (neutral if they are all
+//! neutral). This can be changed with `Generator::set_group_rule`. Unless a number is also
+//! given, plural forms agree with the number of members:
+//!
+//! ```
+//! # use genere::Generator;
+//! let json = r#"
+//! {
+//!     "$meta": {"language": "fr"},
+//!     "hero": ["Marie[f]"],
+//!     "sidekick": ["Julie[f]"],
+//!     "text[hero,sidekick]": ["{hero} et {sidekick} sont parti·e(s)."]
+//! }"#;
+//! let mut gen = Generator::new();
+//! gen.add_json(json).unwrap();
+//! assert_eq!(&gen.instantiate("text").unwrap(), "Marie et Julie sont parties.");
+//! ```
+//!
+//! ## Pronouns
+//!
+//! `{symbol:subj}`, `{symbol:obj}`, `{symbol:poss}` and `{symbol:refl}` are replaced by the
+//! subject, object, possessive and reflexive pronouns of a symbol. By default, these are
+//! the pronouns of the language for the symbol's gender, but custom pronoun sets can be
+//! declared in the metadata (or with `Generator::add_pronouns`), and attached to a symbol
+//! with a `[pronouns:name]` marker instead of a gender. Gendered expressions that depend on
+//! this symbol then agree with the gender given by the set's `agreement` field:
+//!
+//! ```
+//! # use genere::Generator;
+//! let json = r#"
+//! {
+//!     "$meta": {
+//!         "pronouns": {
+//!             "xe": {"subject": "xe", "object": "xem", "possessive": "xyr",
+//!                    "reflexive": "xemself", "agreement": "n"}
+//!         }
+//!     },
+//!     "hero": ["Alex[pronouns:xe]"],
+//!     "villain": ["Igor[m]"],
+//!     "text": ["{Hero:subj} saw {villain:obj} and hid {hero:refl}."]
+//! }"#;
+//! let mut gen = Generator::new();
+//! gen.add_json(json).unwrap();
+//! assert_eq!(&gen.instantiate("text").unwrap(), "Xe saw him and hid xemself.");
+//! ```
+//!
+//! ## Articles
+//!
+//! `{a:symbol}` (or `{an:symbol}`) is replaced by the content of the symbol preceded by the
+//! indefinite article it requires, according to the language: in English, "a" or "an"
+//! depending on the pronunciation of the next word (e.g. "an hour", "a unicorn"), and in
+//! French, "un" or "une" depending on the symbol's gender. `{A:symbol}` capitalizes the
+//! article.
+//!
+//! ```
+//! # use genere::Generator;
+//! let json = r#"
+//! {
+//!     "animal": ["unicorn"],
+//!     "time": ["hour"],
+//!     "text": ["{A:animal} waited for {a:time}."]
+//! }"#;
+//! let mut gen = Generator::new();
+//! gen.add_json(json).unwrap();
+//! assert_eq!(&gen.instantiate("text").unwrap(), "A unicorn waited for an hour.");
+//! ```
+//!
+//! ## Referent gender
+//!
+//! The grammatical gender of a noun can differ from the gender of the person it refers to,
+//! e.g. "la sentinelle" is feminine, but can refer to a man. The gender of the referent
+//! can be given with a `[ref:m]` marker (or a `"referent"` field in the object syntax),
+//! and dependencies use it when the symbol is followed by `:ref`, e.g. `il/elle[person:ref]`.
+//! Pronouns such as `{person:subj}` use the referent gender, if it is set.
+//!
+//! ```
+//! # use genere::Generator;
+//! let json = r#"
+//! {
+//!     "person": ["la sentinelle[f][ref:m]"],
+//!     "text[person]": ["{Person} est arrivé·e. Il/Elle[person:ref] est fatigué·e[person:ref]."]
+//! }"#;
+//! let mut gen = Generator::new();
+//! gen.add_json(json).unwrap();
+//! assert_eq!(&gen.instantiate("text").unwrap(), "La sentinelle est arrivée. Il est fatigué.");
+//! ```
+//!
+//! ## Gender classes
+//!
+//! Grammars that need other genders than the built-in `m`, `f` and `n` (e.g. German
+//! masculine, feminine and neuter, or noun classes) can declare their own gender classes
+//! in the `"genders"` field of the metadata, or with `Generator::set_gender_classes`. Gender
+//! markers are then the names of these classes, and `form1/form2/...` expressions give one
+//! form per class, in the same order, and optionally one more for when the gender isn't
+//! set:
+//!
+//! ```
+//! # use genere::Generator;
+//! let json = r#"
+//! {
+//!     "$meta": {"language": "de", "genders": ["m", "f", "nt"]},
+//!     "object": ["Buch[nt]", "Tisch[m]", "Lampe[f]"],
+//!     "text": ["der/die/das[object] {object}"]
+//! }"#;
+//! let mut gen = Generator::new();
+//! gen.add_json(json).unwrap();
+//! let text = gen.instantiate("text").unwrap();
+//! assert!(["das Buch", "der Tisch", "die Lampe"].contains(&text.as_str()));
+//! ```
+//!
+//! ## Languages
+//!
+//! Language-specific behaviour (plural rules, articles, contractions, rendering of gendered
+//! expressions when the gender is neutral, casing and spelling out numbers) is provided by an
+//! implementation of the `Language` trait. The one that is used depends on the `language`
+//! of the grammar's metadata: genere provides `English` (the default), `French`, `Spanish`,
+//! `Portuguese` and `German`, and other languages use a `GenericLanguage`, which only knows about
+//! their plural rules.
+//!
+//! You can implement `Language` for your own language and register it with
+//! `Generator::register_language`, or force the use of a language profile with
+//! `Generator::set_language`.
+//!
+//! ### Spanish and Portuguese inclusive forms
+//!
+//! In Spanish and Portuguese, words written with an inclusive ending, such as "amig@s",
+//! "amigxs" or "amigos/as", are gendered expressions, like "ami·e" in French. When the
+//! gender is neutral, words ending with "o(s)" in the masculine and "a(s)" in the feminine
+//! use the "e" ending, e.g. "amigues", and articles and pronouns have specific forms, e.g.
+//! "le/les/elle" in Spanish, or "ê/ês/elu" in Portuguese. Another ending can be chosen with
+//! `Spanish::with_ending` or `Portuguese::with_ending`. Words written in the "e" form, such
+//! as "amigues" or "les", and singular words ending with "x", such as "chicx", are also
+//! understood, but only if they have their own dependency, e.g. "amigues[hero]", since
+//! they can be ordinary words too ("ex", "fénix").
+//!
+//! ```
+//! # use genere::Generator;
+//! let json = r#"
+//! {
+//!     "$meta": {"language": "es"},
+//!     "hero": ["Ana[f]"],
+//!     "text[hero]": ["{hero} y sus amig@s: el/la chicx[hero] y los/las amigos/as."]
+//! }"#;
+//! let mut gen = Generator::new();
+//! gen.add_json(json).unwrap();
+//! assert_eq!(&gen.instantiate("text").unwrap(), "Ana y sus amigas: la chica y las amigas.");
+//! ```
+//!
+//! ### German gender markers and articles
+//!
+//! In German, words written with a Gendersternchen ("Lehrer*innen"), a colon
+//! ("Lehrer:innen"), a gender gap ("Lehrer_innen") or a Binnen-I ("LehrerInnen") are
+//! gendered expressions. When the gender is neutral, they are rendered with the
+//! Gendersternchen, or with another marker chosen with `German::with_marker`.
+//!
+//! The articles "der" and "ein" are declined with modifiers for the case (`nom`, `akk`,
+//! `dat` or `gen`) and `pl` for the plural, e.g. `{der.dat.pl:symbol}`. Since the neutral
+//! gender means that the gender isn't known, neuter nouns use the third gender class,
+//! e.g. with `"genders": ["m", "f", "n"]`.
+//!
+//! ```
+//! # use genere::Generator;
+//! let json = r#"
+//! {
+//!     "$meta": {"language": "de", "genders": ["m", "f", "n"]},
+//!     "hero": ["Alex"],
+//!     "buch": ["Buch[n]"],
+//!     "text[hero]": ["{der:hero} Lehrer*in gibt {ein.akk:buch} den Schüler:innen."]
+//! }"#;
+//! let mut gen = Generator::new();
+//! gen.add_json(json).unwrap();
+//! assert_eq!(&gen.instantiate("text").unwrap(),
+//!            "der*die Alex Lehrer*in gibt ein Buch den Schüler*innen.");
+//! ```
+//!
+//! ### Contractions
+//!
+//! Contractions can be enabled with `"contractions": true` in the metadata, or with
+//! `Generator::set_contractions`. For English, this replaces "a" and "an" by the article
+//! required by the next word. For French, this applies elision before a vowel or a mute
+//! h ("le épée" becomes "l'épée", "que il" becomes "qu'il") and contracts "à le", "à les",
+//! "de le" and "de les" to "au", "aux", "du" and "des".
+//!
+//! Only words that come from the grammar are contracted: the forms of gendered expressions,
+//! articles inserted by `{a:symbol}` and words right before a `{symbol}`. Other text is left
+//! as written, so "décidé de le faire" or "prends-le avec toi" are not modified. Words
+//! starting with an aspirated h must escape it, e.g. "~héros", and an escaped space prevents
+//! a contraction, e.g. "de~ le {verbe}".
+//!
+//! ```
+//! # use genere::Generator;
+//! let json = r#"
+//! {
+//!     "$meta": {"language": "fr", "contractions": true},
+//!     "arme": ["épée[f]"],
+//!     "héros": ["~héros"],
+//!     "text": ["Le/La[arme] {arme} de le {héros} a décidé de le garder."]
+//! }"#;
+//! let mut gen = Generator::new();
+//! gen.add_json(json).unwrap();
+//! assert_eq!(&gen.instantiate("text").unwrap(),
+//!            "L'épée du héros a décidé de le garder.");
+//! ```
+//!
+//! ## Typography
+//!
+//! Typographic conventions can be applied to the generated text, with `"typography": true`
+//! in the metadata or `Generator::set_typography`. Straight quotes are replaced by the
+//! language's quotation marks and curly apostrophes, the double spaces left by empty
+//! symbols are collapsed, and spaces before commas and periods are removed. In French,
+//! narrow no-break spaces are also put before ";", ":", "!" and "?", and inside « ».
+//!
+//! ```
+//! # use genere::Generator;
+//! let json = r#"
+//! {
+//!     "$meta": {"language": "fr", "typography": true},
+//!     "adjectif": [""],
+//!     "text": ["Une {adjectif}  épée , dit-il : \"c'est tout !\""]
+//! }"#;
+//! let mut gen = Generator::new();
+//! gen.add_json(json).unwrap();
+//! assert_eq!(&gen.instantiate("text").unwrap(),
+//!            "Une épée, dit-il\u{202F}: «\u{202F}c’est tout\u{202F}!\u{202F}»");
+//! ```
+//!
 //! ## Escaping
 //!
-//! If you want to use the '[', ']', '{', '}', '/' and '·' characters in your text, you can use
-//! the escape character '\~'. E.g., "\~{foo}" will display "{foo}" instead of trying to find the symbol `foo` and replace it with its content. You can also use "~~" if you want to display the tilde symbol.
+//! If you want to use the '[', ']', '{', '}', '/', '·', '(', ')', '<', '|', '.', '@', '*' and ':' characters in your text, you can use
+//! the escape character '\~'. E.g., "\~{foo}" will display "{foo}" instead of trying to find the symbol `foo` and replace it with its content. You can also use "~~" if you want to display the tilde symbol. "~h" marks an aspirated h, which prevents elision when contractions are enabled, and "~=" prevents the capitalization of a word at the start of a sentence.
 //!
 //! ## License
 //!
@@ -185,9 +684,26 @@
 
 
 mod errors;
+mod expr;
 mod generator;
+mod grammar;
+mod language;
+mod numbers;
+mod plural;
+mod pronouns;
+mod tracery;
 
 pub use generator::Generator;
 pub use generator::Gender;
+pub use generator::NeutralStyle;
+pub use generator::GroupRule;
+pub use grammar::{Marker, Metadata};
+pub use language::{
+    English, French, GenericLanguage, German, GermanMarker, InclusiveEnding, Language,
+    Portuguese, Spanish,
+};
+pub use plural::PluralCategory;
+pub use pronouns::PronounSet;
+pub use tracery::tracery_to_json;
 pub use errors::Result;
 
@@ -0,0 +1,182 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with
+// this file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Numbers spelled out in words, as used by the `.words` modifier.
+
+/// Splits a number in groups of three digits, from the lowest to the highest
+fn groups(mut n: u64) -> Vec<u64> {
+    let mut groups = vec![];
+    while n > 0 {
+        groups.push(n % 1000);
+        n /= 1000;
+    }
+    groups
+}
+
+const EN_UNITS: [&str; 20] = [
+    "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten",
+    "eleven", "twelve", "thirteen", "fourteen", "fifteen", "sixteen", "seventeen", "eighteen",
+    "nineteen",
+];
+const EN_TENS: [&str; 10] = [
+    "", "", "twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety",
+];
+const EN_SCALES: [&str; 7] = [
+    "", "thousand", "million", "billion", "trillion", "quadrillion", "quintillion",
+];
+
+/// Spells out a number between 0 and 999 in English
+fn english_below_1000(n: u64) -> String {
+    let (hundreds, rest) = (n / 100, n % 100);
+    let rest = match rest {
+        0 => String::new(),
+        1..=19 => EN_UNITS[rest as usize].to_string(),
+        _ if rest % 10 == 0 => EN_TENS[(rest / 10) as usize].to_string(),
+        _ => format!("{}-{}", EN_TENS[(rest / 10) as usize], EN_UNITS[(rest % 10) as usize]),
+    };
+    match (hundreds, rest.is_empty()) {
+        (0, _) => rest,
+        (h, true) => format!("{} hundred", EN_UNITS[h as usize]),
+        (h, false) => format!("{} hundred and {}", EN_UNITS[h as usize], rest),
+    }
+}
+
+/// Spells out a number in English
+pub(crate) fn english(n: i64) -> String {
+    if n == 0 {
+        return EN_UNITS[0].to_string();
+    }
+    let mut words: Vec<String> = vec![];
+    for (i, group) in groups(n.unsigned_abs()).into_iter().enumerate().rev() {
+        if group == 0 {
+            continue;
+        }
+        let mut s = english_below_1000(group);
+        if i > 0 {
+            s = format!("{} {}", s, EN_SCALES[i]);
+        } else if group < 100 && !words.is_empty() {
+            s = format!("and {}", s);
+        }
+        words.push(s);
+    }
+    let s = words.join(" ");
+    if n < 0 {
+        format!("minus {}", s)
+    } else {
+        s
+    }
+}
+
+const FR_UNITS: [&str; 17] = [
+    "zéro", "un", "deux", "trois", "quatre", "cinq", "six", "sept", "huit", "neuf", "dix",
+    "onze", "douze", "treize", "quatorze", "quinze", "seize",
+];
+const FR_TENS: [&str; 7] = ["", "dix", "vingt", "trente", "quarante", "cinquante", "soixante"];
+const FR_SCALES: [&str; 7] = [
+    "", "mille", "million", "milliard", "billion", "billiard", "trillion",
+];
+
+/// Spells out a number between 1 and 99 in French.
+///
+/// `last` tells whether "quatre-vingts" can take its final "s", which isn't the case
+/// when it is followed by "mille".
+fn french_below_100(n: u64, last: bool) -> String {
+    let (tens, units) = (n / 10, n % 10);
+    match tens {
+        _ if n <= 16 => FR_UNITS[n as usize].to_string(),
+        1 => format!("dix-{}", FR_UNITS[units as usize]),
+        2..=6 => match units {
+            0 => FR_TENS[tens as usize].to_string(),
+            1 => format!("{} et un", FR_TENS[tens as usize]),
+            _ => format!("{}-{}", FR_TENS[tens as usize], FR_UNITS[units as usize]),
+        },
+        7 if units == 1 => "soixante et onze".to_string(),
+        7 => format!("soixante-{}", french_below_100(10 + units, last)),
+        8 if units == 0 && last => "quatre-vingts".to_string(),
+        8 if units == 0 => "quatre-vingt".to_string(),
+        8 => format!("quatre-vingt-{}", french_below_100(units, last)),
+        _ => format!("quatre-vingt-{}", french_below_100(10 + units, last)),
+    }
+}
+
+/// Spells out a number between 1 and 999 in French
+fn french_below_1000(n: u64, last: bool) -> String {
+    let (hundreds, rest) = (n / 100, n % 100);
+    let hundreds = match hundreds {
+        0 => String::new(),
+        1 => "cent".to_string(),
+        h if rest == 0 && last => format!("{} cents", FR_UNITS[h as usize]),
+        h => format!("{} cent", FR_UNITS[h as usize]),
+    };
+    match (hundreds.is_empty(), rest) {
+        (_, 0) => hundreds,
+        (true, _) => french_below_100(rest, last),
+        (false, _) => format!("{} {}", hundreds, french_below_100(rest, last)),
+    }
+}
+
+/// Spells out a number in French
+pub(crate) fn french(n: i64) -> String {
+    if n == 0 {
+        return FR_UNITS[0].to_string();
+    }
+    let mut words: Vec<String> = vec![];
+    for (i, group) in groups(n.unsigned_abs()).into_iter().enumerate().rev() {
+        if group == 0 {
+            continue;
+        }
+        words.push(match i {
+            0 => french_below_1000(group, true),
+            // "mille" is invariable, and so are "vingt" and "cent" before it
+            1 if group == 1 => "mille".to_string(),
+            1 => format!("{} mille", french_below_1000(group, false)),
+            // "million", "milliard" and so on are nouns
+            _ => format!(
+                "{} {}{}",
+                french_below_1000(group, true),
+                FR_SCALES[i],
+                if group > 1 { "s" } else { "" }
+            ),
+        });
+    }
+    let s = words.join(" ");
+    if n < 0 {
+        format!("moins {}", s)
+    } else {
+        s
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////
+//                                    TESTS
+///////////////////////////////////////////////////////////////////////////////////////////
+
+#[test]
+fn english_words() {
+    assert_eq!(english(0), "zero");
+    assert_eq!(english(21), "twenty-one");
+    assert_eq!(english(105), "one hundred and five");
+    assert_eq!(english(1_005), "one thousand and five");
+    assert_eq!(english(2_340_000), "two million three hundred and forty thousand");
+    assert_eq!(english(-40), "minus forty");
+    assert_eq!(english(i64::MIN).split(' ').next(), Some("minus"));
+}
+
+#[test]
+fn french_words() {
+    assert_eq!(french(21), "vingt et un");
+    assert_eq!(french(71), "soixante et onze");
+    assert_eq!(french(77), "soixante-dix-sept");
+    assert_eq!(french(80), "quatre-vingts");
+    assert_eq!(french(81), "quatre-vingt-un");
+    assert_eq!(french(99), "quatre-vingt-dix-neuf");
+    assert_eq!(french(200), "deux cents");
+    assert_eq!(french(201), "deux cent un");
+    assert_eq!(french(1_000), "mille");
+    assert_eq!(french(80_000), "quatre-vingt mille");
+    assert_eq!(french(200_000), "deux cent mille");
+    assert_eq!(french(1_000_000), "un million");
+    assert_eq!(french(80_000_000), "quatre-vingts millions");
+    assert_eq!(french(-1), "moins un");
+}
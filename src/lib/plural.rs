@@ -0,0 +1,130 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with
+// this file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Plural categories, following the [CLDR plural
+//! rules](https://cldr.unicode.org/index/cldr-spec/plural-rules) for integers.
+
+use crate::language::Language;
+
+/// A CLDR plural category
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PluralCategory {
+    /// Zero, e.g. in Arabic
+    Zero,
+    /// Singular
+    One,
+    /// Dual, e.g. in Arabic
+    Two,
+    /// Paucal, e.g. 2 to 4 in Russian
+    Few,
+    /// e.g. 5 to 20 in Russian, or millions in French
+    Many,
+    /// Plural, and everything else
+    Other,
+}
+
+use PluralCategory::*;
+
+/// Returns the language part of a language code, e.g. "pt" for "pt-BR"
+fn code(language: &str) -> String {
+    language
+        .split(['-', '_'])
+        .next()
+        .unwrap_or("")
+        .to_lowercase()
+}
+
+/// Returns the plural categories that integers can have in a language, in the order
+/// in which their forms must be given (e.g. in `apple/apples[#n]`).
+pub(crate) fn categories(language: &str) -> &'static [PluralCategory] {
+    match code(language).as_str() {
+        "fr" | "es" | "it" | "pt" | "ca" => &[One, Many, Other],
+        "ru" | "uk" | "be" | "pl" => &[One, Few, Many],
+        "cs" | "sk" => &[One, Few, Other],
+        "ar" => &[Zero, One, Two, Few, Many, Other],
+        "ja" | "zh" | "ko" | "vi" | "th" | "id" => &[Other],
+        _ => &[One, Other],
+    }
+}
+
+/// Returns the plural category of an integer in a language.
+///
+/// Languages that aren't known use the same rules as English.
+pub(crate) fn category(n: i64, language: &str) -> PluralCategory {
+    let n = n.unsigned_abs();
+    let (n10, n100) = (n % 10, n % 100);
+    let portugal = language.eq_ignore_ascii_case("pt-PT") || language.eq_ignore_ascii_case("pt_PT");
+    match code(language).as_str() {
+        "fr" | "pt" if n <= 1 && !portugal => One,
+        "fr" | "es" | "it" | "pt" | "ca" if n != 0 && n % 1_000_000 == 0 => Many,
+        "fr" | "es" | "it" | "pt" | "ca" if n == 1 => One,
+        "fr" | "es" | "it" | "pt" | "ca" => Other,
+        "ru" | "uk" | "be" if n10 == 1 && n100 != 11 => One,
+        "pl" if n == 1 => One,
+        "ru" | "uk" | "be" | "pl" if (2..=4).contains(&n10) && !(12..=14).contains(&n100) => Few,
+        "ru" | "uk" | "be" | "pl" => Many,
+        "cs" | "sk" => match n {
+            1 => One,
+            2..=4 => Few,
+            _ => Other,
+        },
+        "ar" => match n {
+            0 => Zero,
+            1 => One,
+            2 => Two,
+            _ if (3..=10).contains(&n100) => Few,
+            _ if (11..=99).contains(&n100) => Many,
+            _ => Other,
+        },
+        "ja" | "zh" | "ko" | "vi" | "th" | "id" => Other,
+        _ if n == 1 => One,
+        _ => Other,
+    }
+}
+
+/// Picks the form corresponding to a number, among forms given in the order of
+/// `categories`. If there are fewer forms than categories, the last one is used for
+/// the missing ones.
+pub(crate) fn select<'a>(n: i64, language: &dyn Language, forms: &[&'a str]) -> &'a str {
+    let category = language.plural_category(n);
+    let index = language
+        .plural_categories()
+        .iter()
+        .position(|c| *c == category)
+        .unwrap_or(0);
+    forms[index.min(forms.len() - 1)]
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////
+//                                    TESTS
+///////////////////////////////////////////////////////////////////////////////////////////
+
+#[test]
+fn plural_categories() {
+    assert_eq!(category(1, "en"), One);
+    assert_eq!(category(0, "en"), Other);
+    assert_eq!(category(0, "fr"), One);
+    assert_eq!(category(-1, "fr-FR"), One);
+    assert_eq!(category(2, "fr"), Other);
+    assert_eq!(category(2_000_000, "fr"), Many);
+    assert_eq!(category(0, "pt-BR"), One);
+    assert_eq!(category(0, "pt-PT"), Other);
+    assert_eq!(category(21, "ru"), One);
+    assert_eq!(category(23, "ru"), Few);
+    assert_eq!(category(12, "ru"), Many);
+    assert_eq!(category(21, "pl"), Many);
+    assert_eq!(category(102, "ar"), Other);
+    assert_eq!(category(1, "ja"), Other);
+}
+
+#[test]
+fn plural_forms() {
+    use crate::language::{English, French, GenericLanguage};
+    assert_eq!(select(1, &English, &["apple", "apples"]), "apple");
+    assert_eq!(select(3, &English, &["apple", "apples"]), "apples");
+    assert_eq!(select(1_000_000, &French, &["pomme", "pommes"]), "pommes");
+    let ru = GenericLanguage::new("ru");
+    assert_eq!(select(5, &ru, &["яблоко", "яблока", "яблок"]), "яблок");
+    assert_eq!(select(3, &GenericLanguage::new("ja"), &["りんご"]), "りんご");
+}
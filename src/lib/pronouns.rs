@@ -0,0 +1,62 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with
+// this file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Pronoun sets, used by `{symbol:subj}` and the like.
+
+use crate::generator::Gender;
+
+use serde::{Deserialize, Serialize};
+
+/// A set of pronouns, e.g. "xe/xem/xyr/xemself".
+///
+/// Pronoun sets can be declared in the `"pronouns"` field of a grammar's metadata, or with
+/// `Generator::add_pronouns`, and attached to a symbol with a `[pronouns:name]` marker.
+/// The gender used for grammatical agreement with this symbol is then `agreement`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct PronounSet {
+    /// Subject form, e.g. "xe", used by `{symbol:subj}`
+    pub subject: String,
+    /// Object form, e.g. "xem", used by `{symbol:obj}`
+    pub object: String,
+    /// Possessive form, e.g. "xyr", used by `{symbol:poss}`
+    pub possessive: String,
+    /// Reflexive form, e.g. "xemself", used by `{symbol:refl}`
+    pub reflexive: String,
+    /// Gender used for the grammatical agreement
+    pub agreement: Gender,
+}
+
+impl PronounSet {
+    /// Creates a new pronoun set.
+    pub fn new(
+        subject: &str,
+        object: &str,
+        possessive: &str,
+        reflexive: &str,
+        agreement: Gender,
+    ) -> Self {
+        PronounSet {
+            subject: subject.to_string(),
+            object: object.to_string(),
+            possessive: possessive.to_string(),
+            reflexive: reflexive.to_string(),
+            agreement,
+        }
+    }
+
+    /// Names of the forms
+    pub(crate) const FORMS: [&'static str; 4] = ["subj", "obj", "poss", "refl"];
+
+    /// Returns the form corresponding to `subj`, `obj`, `poss` or `refl`
+    pub(crate) fn form(&self, name: &str) -> Option<&str> {
+        match name {
+            "subj" => Some(&self.subject),
+            "obj" => Some(&self.object),
+            "poss" => Some(&self.possessive),
+            "refl" => Some(&self.reflexive),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,268 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with
+// this file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Translation of [Tracery](http://tracery.io/) grammars into genere syntax.
+
+use crate::errors::Result;
+
+use std::collections::{BTreeMap, HashMap};
+
+use error_chain::bail;
+use serde::Deserialize;
+
+/// A Tracery rule set is either a list of strings or a single string
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Rules {
+    Many(Vec<String>),
+    One(String),
+}
+
+/// A Tracery grammar translated into genere syntax
+#[derive(Debug, Default)]
+pub(crate) struct Translation {
+    /// Symbols and their (translated) replacements
+    pub symbols: BTreeMap<String, Vec<String>>,
+    /// Entry symbol, if the grammar has an `origin`
+    pub entry: Option<String>,
+}
+
+/// Translates a Tracery grammar, in JSON format, into genere syntax.
+///
+/// Returns an error listing all the elements that couldn't be translated, if any.
+pub(crate) fn translate(json: &str) -> Result<Translation> {
+    let grammar: HashMap<String, Rules> = serde_json::from_str(json)?;
+    let mut translation = Translation::default();
+    let mut errors: Vec<String> = vec![];
+
+    let mut names: Vec<&String> = grammar.keys().collect();
+    names.sort();
+    for name in names {
+        if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            errors.push(format!("symbol '{}': name is not a valid genere symbol", name));
+            continue;
+        }
+        let symbol = name.to_lowercase();
+        if translation.symbols.contains_key(&symbol) {
+            errors.push(format!(
+                "symbol '{}': genere symbols are case-insensitive, it conflicts with another one",
+                name
+            ));
+            continue;
+        }
+        if name == "origin" {
+            translation.entry = Some(symbol.clone());
+        }
+
+        let rules = match &grammar[name] {
+            Rules::Many(rules) => rules.iter().map(|s| s.as_str()).collect(),
+            Rules::One(rule) => vec![rule.as_str()],
+        };
+        let mut contents = Vec::with_capacity(rules.len());
+        for rule in rules {
+            match translate_rule(rule) {
+                Ok(content) => contents.push(content),
+                Err(e) => errors.push(format!("symbol '{}': {}", name, e)),
+            }
+        }
+        translation.symbols.insert(symbol, contents);
+    }
+
+    if !errors.is_empty() {
+        bail!(
+            "Could not translate Tracery grammar:\n- {}",
+            errors.join("\n- ")
+        );
+    }
+    Ok(translation)
+}
+
+/// Translates a Tracery grammar, in JSON format, into a genere grammar in JSON format.
+///
+/// Note that, since Tracery picks a new value each time a symbol is referenced,
+/// `#symbol#` is translated to `{{symbol}}`. If some elements can't be translated, an
+/// error listing all of them is returned.
+pub fn tracery_to_json(json: &str) -> Result<String> {
+    let translation = translate(json)?;
+    let mut map = serde_json::Map::new();
+    if let Some(entry) = translation.entry {
+        map.insert("$meta".into(), serde_json::json!({ "entry": entry }));
+    }
+    for (symbol, contents) in translation.symbols {
+        map.insert(symbol, contents.into());
+    }
+    Ok(serde_json::to_string_pretty(&map)?)
+}
+
+/// Escapes a character that has a special meaning in genere, given the character that
+/// follows it.
+///
+/// A period is only special before a letter (e.g. "étudiant.e"), and is otherwise left
+/// as is so that it still ends a sentence. "=" is only special inside braces.
+fn escape(c: char, next: Option<char>, out: &mut String) {
+    if "~[]{}/·()<|@*:".contains(c) || (c == '.' && next.is_some_and(char::is_alphabetic)) {
+        out.push('~');
+    }
+    out.push(c);
+}
+
+/// Translates a single rule
+fn translate_rule(rule: &str) -> std::result::Result<String, String> {
+    let mut out = String::with_capacity(rule.len());
+    let mut chars = rule.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(c) => escape(c, chars.clone().next(), &mut out),
+                None => out.push('\\'),
+            },
+            '#' => {
+                let mut tag = String::new();
+                let mut depth = 0;
+                loop {
+                    match chars.next() {
+                        None => return Err(format!("unclosed '#' in '{}'", rule)),
+                        Some('#') if depth == 0 => break,
+                        Some(c) => {
+                            match c {
+                                '[' => depth += 1,
+                                ']' => depth -= 1,
+                                _ => (),
+                            }
+                            tag.push(c);
+                        }
+                    }
+                }
+                out.push_str(&translate_tag(&tag)?);
+            }
+            '[' => {
+                let action = read_action(&mut chars)
+                    .ok_or_else(|| format!("unclosed '[' in '{}'", rule))?;
+                out.push_str(&translate_action(&action)?);
+            }
+            c => escape(c, chars.clone().next(), &mut out),
+        }
+    }
+    Ok(out)
+}
+
+/// Reads an action, after its opening '[', up to the matching ']'
+fn read_action(chars: &mut std::str::Chars) -> Option<String> {
+    let mut action = String::new();
+    let mut depth = 0;
+    loop {
+        match chars.next()? {
+            ']' if depth == 0 => return Some(action),
+            c => {
+                match c {
+                    '[' => depth += 1,
+                    ']' => depth -= 1,
+                    _ => (),
+                }
+                action.push(c);
+            }
+        }
+    }
+}
+
+/// Translates an action of the form `[variable:rule]` or `[variable:POP]`
+fn translate_action(action: &str) -> std::result::Result<String, String> {
+    let (variable, rule) = match action.find(':') {
+        Some(i) => (&action[..i], &action[i + 1..]),
+        None => return Err(format!("function actions such as '[{}]' are not supported", action)),
+    };
+    if variable.is_empty() || !variable.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        return Err(format!("invalid variable name in action '[{}]'", action));
+    }
+    let variable = variable.to_lowercase();
+    if rule == "POP" {
+        return Ok(format!("{{unset {}}}", variable));
+    }
+    if rule.contains(',') {
+        return Err(format!("actions with multiple rules such as '[{}]' are not supported", action));
+    }
+    let content = translate_rule(rule)?;
+    if content.contains("{set ") || content.contains("{unset ") || content.contains('"') {
+        return Err(format!("action '[{}]' is too complex to be translated", action));
+    }
+    Ok(format!("{{set {} = \"{}\"}}", variable, content))
+}
+
+/// Translates the content of a `#[action]symbol.modifier#` tag
+fn translate_tag(tag: &str) -> std::result::Result<String, String> {
+    let mut out = String::new();
+    let mut chars = tag.chars();
+    let mut rest = tag;
+    while rest.starts_with('[') {
+        chars.next();
+        let action = read_action(&mut chars)
+            .ok_or_else(|| format!("unclosed '[' in '#{}#'", tag))?;
+        out.push_str(&translate_action(&action)?);
+        rest = chars.as_str();
+    }
+    if rest.is_empty() {
+        return Ok(out);
+    }
+
+    let mut parts = rest.split('.');
+    let symbol = parts.next().unwrap_or("");
+    if symbol.is_empty() || !symbol.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        return Err(format!("invalid symbol reference '#{}#'", tag));
+    }
+    let mut symbol = symbol.to_lowercase();
+    let mut article = None;
+    for modifier in parts {
+        match (modifier, article) {
+            // Capitalizes the article if there is one, e.g. for "#animal.a.capitalize#"
+            ("capitalize", Some(_)) => article = Some("A"),
+            ("capitalize", None) => {
+                let mut c = symbol.chars();
+                if let Some(f) = c.next() {
+                    symbol = f.to_uppercase().collect::<String>() + c.as_str();
+                }
+            }
+            ("a", None) => article = Some("a"),
+            _ => return Err(format!("modifier '.{}' is not supported", modifier)),
+        }
+    }
+    // In Tracery, each reference picks a new value
+    match article {
+        Some(article) => out.push_str(&format!("{{{{{}:{}}}}}", article, symbol)),
+        None => out.push_str(&format!("{{{{{}}}}}", symbol)),
+    }
+    Ok(out)
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////
+//                                    TESTS
+///////////////////////////////////////////////////////////////////////////////////////////
+
+#[test]
+fn translate_rules() {
+    assert_eq!(translate_rule("Hello #name#!").unwrap(), "Hello {{name}}!");
+    assert_eq!(translate_rule("#Animal.capitalize#").unwrap(), "{{Animal}}");
+    assert_eq!(translate_rule("and/or {x} \\#").unwrap(), "and~/or ~{x~} #");
+    assert_eq!(translate_rule("#animal.a#").unwrap(), "{{a:animal}}");
+    assert_eq!(translate_rule("#animal.a.capitalize#").unwrap(), "{{A:animal}}");
+    assert_eq!(translate_rule("#animal.capitalize.a#").unwrap(), "{{a:Animal}}");
+    assert!(translate_rule("#animal.s#").is_err());
+    assert!(translate_rule("#animal.a.a#").is_err());
+    assert_eq!(translate_rule("[hero:#name#]#story#").unwrap(), r#"{set hero = "{{name}}"}{{story}}"#);
+    assert_eq!(translate_rule("#[a:x][b:POP]#").unwrap(), r#"{set a = "x"}{unset b}"#);
+    assert!(translate_rule("[foo]").is_err());
+    assert!(translate_rule("#unclosed").is_err());
+}
+
+#[test]
+fn translate_grammar() {
+    let t = translate(r##"{"origin": "#Name# is here", "Name": ["Bob", "Alice"]}"##).unwrap();
+    assert_eq!(t.entry.as_deref(), Some("origin"));
+    assert_eq!(t.symbols["origin"], vec!["{{name}} is here"]);
+
+    let e = translate(r##"{"a": "#b.s#", "B": "x", "b": "y", "c": "[x]"}"##).unwrap_err();
+    let e = e.to_string();
+    assert!(e.contains("'.s'"));
+    assert!(e.contains("case-insensitive"));
+    assert!(e.contains("actions"));
+}